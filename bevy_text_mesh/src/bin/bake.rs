@@ -0,0 +1,238 @@
+//! Bakes the font atlas of a character set to disk, to be loaded as a [`BakedFontAtlas`].
+//!
+//! [`BakedFontAtlas`]: bevy_text_mesh::BakedFontAtlas
+
+use bevy::{
+    asset::Assets,
+    render::texture::Image,
+    tasks::{ComputeTaskPool, TaskPool},
+};
+use bevy_text_mesh::{CharacterSet, Font, FontData, FontVariation, SdfMode};
+use std::{path::PathBuf, process::ExitCode};
+
+const USAGE: &str = "\
+usage: bevy_text_mesh-bake <font> <characters> <output.atlas.json> [options]
+
+  <font>                .ttf/.otf/.ttc/.otc font file
+  <characters>          text file containing the characters to bake, or `ascii`/`latin1`
+  <output.atlas.json>   atlas file to write, pages are written next to it as PNG images
+
+options:
+  --mode <sdf|msdf|mtsdf>   kind of distance field (default sdf)
+  --pixels-per-em <n>       texels per em (default 100)
+  --range <n>               distance range in texels (default 6)
+  --face <n>                face index within a font collection (default 0)
+  --axis <tag>=<value>      variable font axis coordinate, may be repeated
+  --font-path <path>        font path stored in the atlas file, relative to it
+                            (default: the font file name)";
+
+struct Args {
+    font: PathBuf,
+    characters: String,
+    output: PathBuf,
+    mode: SdfMode,
+    pixels_per_em: f64,
+    range: u8,
+    face: u32,
+    variation: FontVariation,
+    font_path: Option<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut parsed = Args {
+            font: PathBuf::new(),
+            characters: String::new(),
+            output: PathBuf::new(),
+            mode: SdfMode::default(),
+            pixels_per_em: 100.0,
+            range: 6,
+            face: 0,
+            variation: FontVariation::default(),
+            font_path: None,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "--mode" => {
+                    parsed.mode = match value()?.as_str() {
+                        "sdf" => SdfMode::Sdf,
+                        "msdf" => SdfMode::Msdf,
+                        "mtsdf" => SdfMode::Mtsdf,
+                        mode => return Err(format!("unknown mode {mode}")),
+                    }
+                }
+                "--pixels-per-em" => {
+                    parsed.pixels_per_em = value()?.parse().map_err(|e| format!("{arg}: {e}"))?
+                }
+                "--range" => parsed.range = value()?.parse().map_err(|e| format!("{arg}: {e}"))?,
+                "--face" => parsed.face = value()?.parse().map_err(|e| format!("{arg}: {e}"))?,
+                "--axis" => {
+                    let axis = value()?;
+                    let (tag, coordinate) = axis
+                        .split_once('=')
+                        .ok_or(format!("invalid axis {axis}, expected <tag>=<value>"))?;
+                    let tag: [u8; 4] = tag
+                        .as_bytes()
+                        .try_into()
+                        .map_err(|_| format!("invalid axis tag {tag}"))?;
+                    let coordinate = coordinate.parse().map_err(|e| format!("{arg}: {e}"))?;
+                    parsed.variation = parsed.variation.with(&tag, coordinate);
+                }
+                "--font-path" => parsed.font_path = Some(value()?),
+                "-h" | "--help" => return Err(String::new()),
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}")),
+                _ => positional.push(arg),
+            }
+        }
+        let [font, characters, output] = <[String; 3]>::try_from(positional)
+            .map_err(|_| "expected <font> <characters> <output.atlas.json>".to_owned())?;
+        parsed.font = font.into();
+        parsed.characters = characters;
+        parsed.output = output.into();
+        Ok(parsed)
+    }
+}
+
+fn bake(args: Args) -> Result<(), String> {
+    let bytes = std::fs::read(&args.font).map_err(|e| format!("{}: {e}", args.font.display()))?;
+    let face = owned_ttf_parser::OwnedFace::from_vec(bytes, args.face)
+        .map_err(|e| format!("{}: {e}", args.font.display()))?;
+    let font = Font::from(face)
+        .with_mode(args.mode)
+        .with_pixels_per_em(args.pixels_per_em)
+        .with_range(args.range)
+        .instance(&args.variation);
+
+    let characters = match args.characters.as_str() {
+        "ascii" => CharacterSet::Ascii,
+        "latin1" => CharacterSet::Latin1,
+        path => {
+            CharacterSet::Text(std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?)
+        }
+    };
+    let glyph_ids: Vec<_> = characters
+        .chars()
+        .into_iter()
+        .filter_map(|c| {
+            let glyph_id = font.glyph_id(c);
+            if glyph_id.is_none() {
+                eprintln!("warning: no glyph for {c:?}");
+            }
+            glyph_id
+        })
+        .collect();
+
+    ComputeTaskPool::get_or_init(TaskPool::default);
+    let mut textures = Assets::<Image>::default();
+    let mut font_data = FontData::from(&font);
+    let count = font_data.prewarm(&glyph_ids, &font, &mut textures);
+
+    let font_path = args.font_path.unwrap_or_else(|| {
+        args.font
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+    if let Some(parent) = args.output.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("{}: {e}", parent.display()))?;
+    }
+    font_data
+        .bake(&args.output, font_path, &args.variation, &textures)
+        .map_err(|e| format!("{}: {e}", args.output.display()))?;
+    println!(
+        "baked {count} glyphs into {} page(s) at {}",
+        font_data.atlas_count(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(std::env::args().skip(1)).and_then(bake);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if !error.is_empty() {
+                eprintln!("error: {error}");
+            }
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Args, String> {
+        Args::parse(args.split_whitespace().map(str::to_owned))
+    }
+
+    fn error(args: &str) -> Option<String> {
+        parse(args).err()
+    }
+
+    #[test]
+    fn parses_defaults() {
+        let args = parse("font.ttf ascii font.atlas.json").unwrap();
+        assert_eq!(args.font, PathBuf::from("font.ttf"));
+        assert_eq!(args.characters, "ascii");
+        assert_eq!(args.output, PathBuf::from("font.atlas.json"));
+        assert_eq!(args.mode, SdfMode::Sdf);
+        assert_eq!(args.pixels_per_em, 100.0);
+        assert_eq!(args.range, 6);
+        assert_eq!(args.face, 0);
+        assert!(args.variation.is_default());
+        assert_eq!(args.font_path, None);
+    }
+
+    #[test]
+    fn parses_options_between_positionals() {
+        let args = parse(
+            "--mode mtsdf font.ttc --pixels-per-em 48.5 latin1 --range 8 --face 2 \
+             out.atlas.json --font-path ../fonts/font.ttc --axis wght=700 --axis wdth=75 \
+             --axis wght=300",
+        )
+        .unwrap();
+        assert_eq!(args.font, PathBuf::from("font.ttc"));
+        assert_eq!(args.characters, "latin1");
+        assert_eq!(args.output, PathBuf::from("out.atlas.json"));
+        assert_eq!(args.mode, SdfMode::Mtsdf);
+        assert_eq!(args.pixels_per_em, 48.5);
+        assert_eq!(args.range, 8);
+        assert_eq!(args.face, 2);
+        assert_eq!(
+            args.variation,
+            FontVariation::new()
+                .with(b"wdth", 75.0)
+                .with(b"wght", 300.0)
+        );
+        assert_eq!(args.font_path.as_deref(), Some("../fonts/font.ttc"));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let positionals = "expected <font> <characters> <output.atlas.json>";
+        assert_eq!(error("font.ttf ascii").as_deref(), Some(positionals));
+        assert_eq!(error("a b c d").as_deref(), Some(positionals));
+        assert_eq!(
+            error("a b c --range").as_deref(),
+            Some("missing value for --range")
+        );
+        assert_eq!(
+            error("a b c --size 12").as_deref(),
+            Some("unknown option --size")
+        );
+        assert_eq!(
+            error("a b c --mode psdf").as_deref(),
+            Some("unknown mode psdf")
+        );
+        assert!(error("a b c --range 256").is_some());
+        assert!(error("a b c --axis wght").is_some());
+        assert!(error("a b c --axis weight=700").is_some());
+        assert_eq!(error("a b c --help").as_deref(), Some(""));
+    }
+}
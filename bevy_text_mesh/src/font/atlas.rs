@@ -1,124 +1,565 @@
 use bevy::{
-    asset::{AssetId, Assets, Handle},
+    asset::{AssetEvent, AssetId, Assets, Handle},
     log,
-    math::{Rect, UVec2},
-    prelude::Resource,
+    math::{Rect, URect, UVec2},
+    prelude::{Entity, EventReader, ResMut, Resource},
     render::{
         render_asset::RenderAssetUsages,
-        render_resource::{Extent3d, TextureDimension, TextureFormat},
-        texture::Image,
+        render_resource::{
+            Extent3d, TextureDimension, TextureViewDescriptor, TextureViewDimension,
+        },
+        texture::{Image, TextureFormatPixelInfo},
     },
-    sprite::{DynamicTextureAtlasBuilder, TextureAtlasLayout},
+    sprite::TextureAtlasLayout,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, ComputeTaskPool, Task},
     utils::{HashMap, HashSet},
 };
+use guillotiere::{size2, AtlasAllocator};
 
-use super::font::{Font, GlyphId, GlyphInfo};
+use super::baked::BakedFontAtlas;
+use super::charset::CharacterSet;
+use super::font::{Font, FontVariation, GlyphId, GlyphInfo, SdfMode};
 
 const ATLAS_BASE_SIZE: u32 = 1024;
+// initial layer size of atlases growing with `AtlasGrowth::Double`
+const ATLAS_DOUBLE_BASE_SIZE: u32 = 256;
+// gap between glyphs in generated atlases
+const ATLAS_PADDING: u32 = 1;
 
+/// How a [`FontData`] makes room for glyphs that fit into none of its atlases.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AtlasGrowth {
+    /// Adds another layer to the atlas texture array.
+    #[default]
+    NewAtlas,
+    /// Starts with a small layer and doubles its size up to `max_size` texels per side, keeping
+    /// the glyphs in place, before adding further layers. All layers share one size, so once
+    /// there are several, growing them would multiply the memory of every page.
+    Double { max_size: u32 },
+}
+
+/// A layer of the atlas texture array of a [`FontData`].
 pub struct FontAtlas {
-    pub dynamic_texture_atlas_builder: DynamicTextureAtlasBuilder,
+    allocator: AtlasAllocator,
     pub glyph_to_atlas_index: HashMap<GlyphId, usize>,
     pub texture_atlas: TextureAtlasLayout,
-    pub texture: Handle<Image>,
+    /// Baked atlases are loaded from disk, their glyph rects are stored without padding and no
+    /// further glyphs can be packed into them.
+    pub baked: bool,
 }
 
 impl FontAtlas {
-    pub fn new(
-        textures: &mut Assets<Image>,
-        // materials: &mut Assets<SdfMaterial>,
-        size: UVec2,
-    ) -> FontAtlas {
-        let texture = textures.add(Image::new_fill(
-            Extent3d {
-                width: size.x as u32,
-                height: size.y as u32,
-                depth_or_array_layers: 1,
-            },
-            TextureDimension::D2,
-            &[0, 0, 0, 0],
-            TextureFormat::Rgba8UnormSrgb,
-            // Need to keep this image CPU persistent in order to add additional glyphs later on
-            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-        ));
+    pub fn new(size: UVec2) -> FontAtlas {
         Self {
-            texture_atlas: TextureAtlasLayout::new_empty(size),
+            allocator: AtlasAllocator::new(size2(size.x as i32, size.y as i32)),
             glyph_to_atlas_index: HashMap::default(),
-            dynamic_texture_atlas_builder: DynamicTextureAtlasBuilder::new(size, 1),
-            texture,
+            texture_atlas: TextureAtlasLayout::new_empty(size),
+            baked: false,
         }
     }
 
+    /// Creates an atlas for a baked page with the given unpadded glyph rects.
+    pub fn from_baked(
+        size: UVec2,
+        glyphs: impl IntoIterator<Item = (GlyphId, URect)>,
+    ) -> FontAtlas {
+        let mut atlas = Self::new(size);
+        atlas.glyph_to_atlas_index = glyphs
+            .into_iter()
+            .map(|(glyph_id, rect)| (glyph_id, atlas.texture_atlas.add_texture(rect)))
+            .collect();
+        atlas.baked = true;
+        atlas
+    }
+
+    /// Packs the glyph into this atlas, which is the layer `layer` of `texture`.
     pub fn add_glyph(
         &mut self,
-        textures: &mut Assets<Image>,
+        texture: &mut Image,
+        layer: usize,
         glyph_id: GlyphId,
-        texture: &Image,
+        glyph_texture: &Image,
     ) -> bool {
-        if let Some(index) = self.dynamic_texture_atlas_builder.add_texture(
-            &mut self.texture_atlas,
-            textures,
-            &texture,
-            &self.texture,
-        ) {
-            self.glyph_to_atlas_index.insert(glyph_id, index);
-            true
-        } else {
-            false
+        if self.baked {
+            return false;
         }
+        let Some(allocation) = self.allocator.allocate(size2(
+            (glyph_texture.width() + ATLAS_PADDING) as i32,
+            (glyph_texture.height() + ATLAS_PADDING) as i32,
+        )) else {
+            return false;
+        };
+        let min = UVec2::new(
+            allocation.rectangle.min.x as u32,
+            allocation.rectangle.min.y as u32,
+        );
+        let rect = URect::from_corners(min, min + glyph_texture.size());
+        write_rect(texture, layer, rect, &glyph_texture.data);
+        let index = self.texture_atlas.add_texture(rect);
+        self.glyph_to_atlas_index.insert(glyph_id, index);
+        true
+    }
+
+    /// Copies the padded distance field of a glyph out of the layer `layer` of `texture`.
+    fn glyph_texture(&self, glyph_id: GlyphId, texture: &Image, layer: usize) -> Option<Image> {
+        let rect = self
+            .glyph_to_atlas_index
+            .get(&glyph_id)
+            .and_then(|index| self.texture_atlas.textures.get(*index))?;
+        Some(Image::new(
+            Extent3d {
+                width: rect.width(),
+                height: rect.height(),
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            read_rect(texture, layer, *rect),
+            texture.texture_descriptor.format,
+            RenderAssetUsages::MAIN_WORLD,
+        ))
+    }
+
+    /// Removes all glyphs, the texture layer has to be cleared separately.
+    fn clear(&mut self) {
+        *self = Self::new(self.texture_atlas.size);
+    }
+
+    /// Enlarges the atlas, keeping the glyphs in place.
+    fn grow(&mut self, size: UVec2) {
+        self.allocator.grow(size2(size.x as i32, size.y as i32));
+        self.texture_atlas.size = size;
     }
 
-    pub fn lookup_glyph(&self, glyph_id: GlyphId, range: u8) -> Option<Rect> {
+    /// The glyphs in this atlas with their padded distance fields.
+    fn glyph_textures(&self, texture: &Image, layer: usize) -> Vec<(GlyphId, Image)> {
+        self.glyph_to_atlas_index
+            .keys()
+            .filter_map(|glyph_id| {
+                self.glyph_texture(*glyph_id, texture, layer)
+                    .map(|texture| (*glyph_id, texture))
+            })
+            .collect()
+    }
+
+    /// Rect of the glyph in texels, without the padding of `range` texels around its outline.
+    pub fn glyph_rect(&self, glyph_id: GlyphId, range: u8) -> Option<URect> {
+        let padding = if self.baked { 0 } else { range as i32 };
         self.glyph_to_atlas_index
             .get(&glyph_id)
             .and_then(|index| self.texture_atlas.textures.get(*index))
-            .map(|rect| {
-                let size_inv = 1f32 / self.texture_atlas.size.as_vec2();
-                let rect = rect.inflate(-(range as i32)).as_rect();
-                Rect::from_corners(rect.min * size_inv, rect.max * size_inv)
-            })
+            .map(|rect| rect.inflate(-padding))
+    }
+}
+
+/// Byte offset of the texel `(x, y)` of layer `layer` within `texture`.
+fn texel_offset(texture: &Image, layer: usize, x: u32, y: u32) -> usize {
+    let size = texture.size();
+    let texel = (layer * size.y as usize + y as usize) * size.x as usize + x as usize;
+    texel * texture.texture_descriptor.format.pixel_size()
+}
+
+/// Copies tightly packed texels into `rect` of a texture layer.
+fn write_rect(texture: &mut Image, layer: usize, rect: URect, data: &[u8]) {
+    let row_size = rect.width() as usize * texture.texture_descriptor.format.pixel_size();
+    for (row, y) in (rect.min.y..rect.max.y).enumerate() {
+        let start = texel_offset(texture, layer, rect.min.x, y);
+        texture.data[start..start + row_size]
+            .copy_from_slice(&data[row * row_size..(row + 1) * row_size]);
     }
 }
 
+/// Copies the texels in `rect` of a texture layer, tightly packed.
+fn read_rect(texture: &Image, layer: usize, rect: URect) -> Vec<u8> {
+    let row_size = rect.width() as usize * texture.texture_descriptor.format.pixel_size();
+    (rect.min.y..rect.max.y)
+        .flat_map(|y| {
+            let start = texel_offset(texture, layer, rect.min.x, y);
+            &texture.data[start..start + row_size]
+        })
+        .copied()
+        .collect()
+}
+
+/// Generated glyphs and their atlases for a font instance.
+///
+/// All atlases are layers of a single texture array, so a text mesh needs only one mesh and
+/// material per font.
 pub struct FontData {
-    atlases: Vec<FontAtlas>,
-    added: HashSet<char>,
-    code_point_to_atlas: HashMap<char, usize>,
-    code_point_to_glyph_info: HashMap<char, GlyphInfo>,
-    range: u8,
-    line_gap: f64,
+    pub(super) atlases: Vec<FontAtlas>,
+    // the texture array holding one layer per atlas, created with the first atlas
+    texture: Option<Handle<Image>>,
+    // size of each layer in texels
+    layer_size: UVec2,
+    added: HashSet<GlyphId>,
+    // atlas and rect in texels, without padding, of each packed glyph
+    pub(super) glyph_locations: HashMap<GlyphId, (usize, URect)>,
+    pub(super) glyph_infos: HashMap<GlyphId, GlyphInfo>,
+    // glyphs whose distance field is still being generated
+    pending: HashMap<GlyphId, Task<Option<Image>>>,
+    // number of users of each glyph, see `FontAtlases::set_glyph_users`
+    use_counts: HashMap<GlyphId, usize>,
+    // tick at which glyphs without users lost their last one or were added
+    unused_since: HashMap<GlyphId, u64>,
+    growth: AtlasGrowth,
+    // whether glyphs moved within the atlases since the last `FontAtlases::poll_pending`
+    repacked: bool,
+    pub(super) range: u8,
+    pub(super) line_gap: f64,
+    pub(super) mode: SdfMode,
 }
 
 impl FontData {
     pub fn from(face: &Font) -> Self {
         Self {
             atlases: vec![],
+            texture: None,
+            layer_size: UVec2::ZERO,
             added: Default::default(),
-            code_point_to_atlas: Default::default(),
-            code_point_to_glyph_info: Default::default(),
-            range: 6,
+            glyph_locations: Default::default(),
+            glyph_infos: Default::default(),
+            pending: Default::default(),
+            use_counts: Default::default(),
+            unused_since: Default::default(),
+            growth: Default::default(),
+            repacked: false,
+            range: face.range(),
             line_gap: face.line_gap(),
+            mode: face.mode(),
+        }
+    }
+
+    pub fn from_baked(baked: &BakedFontAtlas, textures: &mut Assets<Image>) -> Self {
+        let mut font_data = Self {
+            atlases: vec![],
+            texture: None,
+            layer_size: UVec2::ZERO,
+            added: Default::default(),
+            glyph_locations: Default::default(),
+            glyph_infos: Default::default(),
+            pending: Default::default(),
+            use_counts: Default::default(),
+            unused_since: Default::default(),
+            growth: Default::default(),
+            repacked: false,
+            range: baked.range,
+            line_gap: baked.line_gap,
+            mode: baked.mode,
+        };
+        font_data.add_baked(baked, textures);
+        font_data
+    }
+
+    /// Appends the pages of a baked atlas as new layers, glyphs that were already added keep
+    /// their place.
+    pub fn add_baked(&mut self, baked: &BakedFontAtlas, textures: &mut Assets<Image>) {
+        if baked.mode != self.mode {
+            log::warn!(
+                "Ignoring baked {:?} atlas for a font using {:?}",
+                baked.mode,
+                self.mode
+            );
+            return;
+        }
+        let page_size = baked
+            .page_sizes
+            .iter()
+            .fold(self.layer_size, |size, page_size| size.max(*page_size));
+        if page_size != self.layer_size {
+            self.resize_layers(page_size, textures);
+        }
+
+        let first_page = self.atlases.len();
+        for page in 0..baked.pages.len() {
+            let glyphs = baked
+                .glyphs
+                .iter()
+                .filter(|glyph| glyph.page == page && !self.added.contains(&glyph.id()))
+                .map(|glyph| (glyph.id(), glyph.rect()));
+            self.atlases
+                .push(FontAtlas::from_baked(self.layer_size, glyphs));
+        }
+        let pages: Vec<_> = baked
+            .pages
+            .iter()
+            .zip(&baked.page_sizes)
+            .enumerate()
+            .filter_map(|(page, (page_texture, size))| {
+                let Some(page_texture) = textures.get(page_texture) else {
+                    log::warn!("Baked atlas page {page} is not loaded");
+                    return None;
+                };
+                Some((first_page + page, *size, page_texture.data.clone()))
+            })
+            .collect();
+        let texture = self.update_texture(textures);
+        for (layer, size, data) in pages {
+            write_rect(
+                texture,
+                layer,
+                URect::from_corners(UVec2::ZERO, size),
+                &data,
+            );
+        }
+        for glyph in baked.glyphs.iter() {
+            if self.added.insert(glyph.id()) {
+                self.glyph_locations
+                    .insert(glyph.id(), (first_page + glyph.page, glyph.rect()));
+                self.glyph_infos.insert(glyph.id(), glyph.info());
+            }
         }
     }
 
-    pub fn has_glyph(&self, code_point: char) -> bool {
-        self.added.contains(&code_point)
+    pub fn with_growth(mut self, growth: AtlasGrowth) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    pub fn set_growth(&mut self, growth: AtlasGrowth) {
+        self.growth = growth;
+    }
+
+    pub fn growth(&self) -> AtlasGrowth {
+        self.growth
+    }
+
+    /// The font generating glyphs for these atlases, whose mode may differ from the one of
+    /// `font` when the atlases were baked.
+    fn generator(&self, font: &Font) -> Font {
+        font.clone().with_mode(self.mode).with_range(self.range)
+    }
+
+    pub fn has_glyph(&self, glyph_id: GlyphId) -> bool {
+        self.added.contains(&glyph_id)
     }
 
     pub fn add_glyph(
         &mut self,
-        code_point: char,
+        glyph_id: GlyphId,
         font: &Font,
         textures: &mut Assets<Image>,
     ) -> Option<usize> {
-        self.added.insert(code_point);
-        let Some(glyph_info) = font.glyph(code_point) else {
-            log::warn!("No glyph generated for {code_point}. No glyph data available");
+        self.added.insert(glyph_id);
+        let glyph_info = font.glyph_info(glyph_id);
+        self.glyph_infos.insert(glyph_id, glyph_info.clone());
+        let Some(glyph_texture) = self.generator(font).generate(glyph_id, self.range as f64) else {
+            log::warn!("No glyph generated for {glyph_id:?}. No outline available");
             return None;
         };
-        self.code_point_to_glyph_info
-            .insert(code_point, glyph_info.clone());
-        let glyph_texture = font.generate(glyph_info.id, self.range as f64)?;
+        Some(self.insert_glyph(glyph_id, &glyph_texture, textures))
+    }
+
+    /// Starts generating the glyph on the [`AsyncComputeTaskPool`], it is added to an atlas by
+    /// [`FontData::poll_pending`] once finished.
+    pub fn request_glyph(&mut self, glyph_id: GlyphId, font: &Font) {
+        self.added.insert(glyph_id);
+        self.glyph_infos.insert(glyph_id, font.glyph_info(glyph_id));
+        let font = self.generator(font);
+        let range = self.range as f64;
+        let task = AsyncComputeTaskPool::get().spawn(async move { font.generate(glyph_id, range) });
+        self.pending.insert(glyph_id, task);
+    }
+
+    /// Adds all finished glyphs to the atlases, returns whether any glyph was added.
+    pub fn poll_pending(&mut self, textures: &mut Assets<Image>) -> bool {
+        let mut finished = Vec::new();
+        self.pending.retain(|glyph_id, task| {
+            if !task.is_finished() {
+                return true;
+            }
+            match block_on(poll_once(task)) {
+                Some(Some(texture)) => finished.push((*glyph_id, texture)),
+                Some(None) => {
+                    log::warn!("No glyph generated for {glyph_id:?}. No outline available")
+                }
+                None => return true,
+            }
+            false
+        });
+        for (glyph_id, texture) in finished.iter() {
+            self.insert_glyph(*glyph_id, texture, textures);
+        }
+        !finished.is_empty()
+    }
+
+    /// Whether the glyph was requested but its distance field is not generated yet.
+    pub fn is_pending(&self, glyph_id: GlyphId) -> bool {
+        self.pending.contains_key(&glyph_id)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Number of users of the glyph, e.g. text meshes displaying it.
+    pub fn use_count(&self, glyph_id: GlyphId) -> usize {
+        self.use_counts.get(&glyph_id).copied().unwrap_or_default()
+    }
+
+    fn retain_glyph(&mut self, glyph_id: GlyphId) {
+        *self.use_counts.entry(glyph_id).or_default() += 1;
+        self.unused_since.remove(&glyph_id);
+    }
+
+    fn release_glyph(&mut self, glyph_id: GlyphId, tick: u64) {
+        if let Some(count) = self.use_counts.get_mut(&glyph_id) {
+            *count -= 1;
+            if *count == 0 {
+                self.use_counts.remove(&glyph_id);
+                self.unused_since.insert(glyph_id, tick);
+            }
+        }
+    }
+
+    /// Marks the glyphs that have no users as unused since `tick`, unless they already are.
+    fn track_unused(&mut self, glyph_ids: &[GlyphId], tick: u64) {
+        for glyph_id in glyph_ids {
+            if self.has_glyph(*glyph_id) && !self.use_counts.contains_key(glyph_id) {
+                self.unused_since.entry(*glyph_id).or_insert(tick);
+            }
+        }
+    }
+
+    /// Generated glyphs without users that can be evicted, least recently used first.
+    fn unused_glyphs(&self) -> Vec<GlyphId> {
+        let mut unused: Vec<_> = self
+            .glyph_locations
+            .iter()
+            .filter(|(glyph_id, (atlas, _))| {
+                !self.atlases[*atlas].baked && !self.use_counts.contains_key(*glyph_id)
+            })
+            .map(|(glyph_id, _)| {
+                let since = self.unused_since.get(glyph_id).copied().unwrap_or_default();
+                (since, *glyph_id)
+            })
+            .collect();
+        unused.sort();
+        unused.into_iter().map(|(_, glyph_id)| glyph_id).collect()
+    }
+
+    /// Removes the glyphs and repacks the remaining generated glyphs, trailing atlases left
+    /// empty are freed. Baked atlases are not changed.
+    pub fn evict(&mut self, glyph_ids: &[GlyphId], textures: &mut Assets<Image>) {
+        for glyph_id in glyph_ids {
+            self.added.remove(glyph_id);
+            self.glyph_locations.remove(glyph_id);
+            self.glyph_infos.remove(glyph_id);
+            self.use_counts.remove(glyph_id);
+            self.unused_since.remove(glyph_id);
+        }
+
+        let mut remaining = Vec::new();
+        if let Some(texture) = self
+            .texture
+            .as_ref()
+            .and_then(|texture| textures.get_mut(texture))
+        {
+            for (layer, atlas) in self.atlases.iter_mut().enumerate() {
+                if atlas.baked {
+                    continue;
+                }
+                remaining.extend(
+                    atlas
+                        .glyph_textures(texture, layer)
+                        .into_iter()
+                        .filter(|(glyph_id, _)| self.glyph_locations.contains_key(glyph_id)),
+                );
+                atlas.clear();
+                let (start, end) = (
+                    texel_offset(texture, layer, 0, 0),
+                    texel_offset(texture, layer + 1, 0, 0),
+                );
+                texture.data[start..end].fill(0);
+            }
+        }
+        // packing the largest glyphs first wastes less atlas space
+        remaining.sort_by_key(|(_, texture)| std::cmp::Reverse(texture.height()));
+        for (glyph_id, texture) in remaining.iter() {
+            self.insert_glyph(*glyph_id, texture, textures);
+        }
+
+        // the first layer is kept, the texture array can not be empty
+        let old_count = self.atlases.len();
+        while self.atlases.len() > 1
+            && self
+                .atlases
+                .last()
+                .is_some_and(|atlas| !atlas.baked && atlas.glyph_to_atlas_index.is_empty())
+        {
+            self.atlases.pop();
+        }
+        if self.atlases.len() != old_count {
+            self.update_texture(textures);
+        }
+    }
+
+    /// Size of the atlas texture in bytes.
+    pub fn memory_usage(&self) -> usize {
+        let texel_count = self.layer_size.element_product() as usize * self.atlases.len();
+        texel_count * self.mode.texture_format().pixel_size()
+    }
+
+    /// Generates all glyphs that were not requested yet in parallel on the [`ComputeTaskPool`],
+    /// blocking until they are packed. Returns the number of glyphs added.
+    pub fn prewarm(
+        &mut self,
+        glyph_ids: &[GlyphId],
+        font: &Font,
+        textures: &mut Assets<Image>,
+    ) -> usize {
+        let mut requested: Vec<GlyphId> = glyph_ids
+            .iter()
+            .copied()
+            .filter(|glyph_id| !self.has_glyph(*glyph_id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        for glyph_id in requested.iter() {
+            self.added.insert(*glyph_id);
+            self.glyph_infos
+                .insert(*glyph_id, font.glyph_info(*glyph_id));
+        }
+        // packing the largest glyphs first wastes less atlas space
+        requested.sort_by(|a, b| {
+            let size = |glyph_id| self.glyph_infos[glyph_id].size.y;
+            size(b).total_cmp(&size(a))
+        });
+
+        let font = &self.generator(font);
+        let range = self.range as f64;
+        let generated = ComputeTaskPool::get().scope(|scope| {
+            for glyph_id in requested.iter() {
+                scope.spawn(async move { (*glyph_id, font.generate(*glyph_id, range)) });
+            }
+        });
+        generated
+            .into_iter()
+            .filter_map(|(glyph_id, texture)| {
+                texture.map(|texture| self.insert_glyph(glyph_id, &texture, textures))
+            })
+            .count()
+    }
+
+    fn insert_glyph(
+        &mut self,
+        glyph_id: GlyphId,
+        glyph_texture: &Image,
+        textures: &mut Assets<Image>,
+    ) -> usize {
+        if self.atlases.is_empty() {
+            let base_size = match self.growth {
+                AtlasGrowth::NewAtlas => ATLAS_BASE_SIZE,
+                AtlasGrowth::Double { max_size } => ATLAS_DOUBLE_BASE_SIZE.min(max_size),
+            };
+            self.layer_size = self.layer_size.max(UVec2::splat(base_size));
+        }
+        // every layer has the same size, make sure it can hold the glyph
+        let padded_size = glyph_texture.size() + ATLAS_PADDING;
+        if padded_size.cmpgt(self.layer_size).any() {
+            let containing = UVec2::splat(padded_size.max_element().next_power_of_two());
+            self.resize_layers(self.layer_size.max(containing), textures);
+        }
+
+        let texture = self.update_texture(textures);
         let atlas_index = self
             .atlases
             .iter_mut()
@@ -126,88 +567,724 @@ impl FontData {
             .find_map(|(index, atlas)| {
                 // find a texture atlas with enough space to hold the glyph
                 atlas
-                    .add_glyph(textures, glyph_info.id, &glyph_texture)
+                    .add_glyph(texture, index, glyph_id, glyph_texture)
                     .then_some(index)
             })
+            .or_else(|| self.grow_atlas(glyph_id, glyph_texture, textures))
             .unwrap_or_else(|| {
-                // otherwise create a new texture atlas
-                // Pick the higher of 1024 or the smallest power of 2 greater than glyph_max_size
-                let glyph_max_size: u32 = glyph_texture.width().max(glyph_texture.height());
-                let containing =
-                    (1u32 << (32 - glyph_max_size.leading_zeros())).max(ATLAS_BASE_SIZE);
-                let mut atlas = FontAtlas::new(textures, UVec2::new(containing, containing));
-                if !atlas.add_glyph(textures, glyph_info.id, &glyph_texture) {
+                // otherwise add a new layer
+                let index = self.atlases.len();
+                self.atlases.push(FontAtlas::new(self.layer_size));
+                let texture = self.update_texture(textures);
+                if !self.atlases[index].add_glyph(texture, index, glyph_id, glyph_texture) {
                     log::error!("Failed adding glyph!");
                 }
-                let idx = self.atlases.len();
-                self.atlases.push(atlas);
-                idx
+                index
             });
-        self.code_point_to_atlas.insert(code_point, atlas_index);
-        Some(atlas_index)
+        if let Some(rect) = self.atlases[atlas_index].glyph_rect(glyph_id, self.range) {
+            self.glyph_locations.insert(glyph_id, (atlas_index, rect));
+        }
+        atlas_index
+    }
+
+    /// Doubles the size of the only layer according to [`AtlasGrowth::Double`] and adds the
+    /// glyph to the enlarged atlas. Returns the atlas of the new glyph.
+    fn grow_atlas(
+        &mut self,
+        glyph_id: GlyphId,
+        glyph_texture: &Image,
+        textures: &mut Assets<Image>,
+    ) -> Option<usize> {
+        let AtlasGrowth::Double { max_size } = self.growth else {
+            return None;
+        };
+        let size = self.layer_size * 2;
+        // baked atlases take no further glyphs, growing them makes no room
+        if size.max_element() > max_size
+            || self.atlases.len() > 1
+            || self.atlases.iter().all(|atlas| atlas.baked)
+        {
+            return None;
+        }
+        self.resize_layers(size, textures);
+        Some(self.insert_glyph(glyph_id, glyph_texture, textures))
+    }
+
+    /// Resizes all layers, keeping the glyphs in place at different texture coordinates.
+    fn resize_layers(&mut self, size: UVec2, textures: &mut Assets<Image>) {
+        for atlas in self.atlases.iter_mut() {
+            atlas.grow(size);
+        }
+        self.layer_size = size;
+        if !self.atlases.is_empty() {
+            self.update_texture(textures);
+            self.repacked = true;
+        }
+    }
+
+    /// The texture array with a layer per atlas, created or resized if needed.
+    fn update_texture<'a>(&mut self, textures: &'a mut Assets<Image>) -> &'a mut Image {
+        let size = Extent3d {
+            width: self.layer_size.x,
+            height: self.layer_size.y,
+            depth_or_array_layers: self.atlases.len().max(1) as u32,
+        };
+        let handle = match &self.texture {
+            Some(texture) if textures.contains(texture) => texture.clone(),
+            _ => {
+                let mut texture = Image::new_fill(
+                    size,
+                    TextureDimension::D2,
+                    &vec![0; self.mode.texture_format().pixel_size()],
+                    self.mode.texture_format(),
+                    // Need to keep this image CPU persistent in order to add additional glyphs later on
+                    RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+                );
+                // a single layer would be viewed as a plain 2D texture otherwise
+                texture.texture_view_descriptor = Some(TextureViewDescriptor {
+                    dimension: Some(TextureViewDimension::D2Array),
+                    ..Default::default()
+                });
+                let texture = textures.add(texture);
+                self.texture = Some(texture.clone());
+                texture
+            }
+        };
+        let texture = textures
+            .get_mut(&handle)
+            .expect("atlas texture was just checked or added");
+        if texture.texture_descriptor.size != size {
+            resize_layers(texture, size);
+        }
+        texture
     }
 
-    pub fn glyph_info(&self, code_point: char) -> Option<&GlyphInfo> {
-        self.code_point_to_glyph_info.get(&code_point)
+    pub fn glyph_info(&self, glyph_id: GlyphId) -> Option<&GlyphInfo> {
+        self.glyph_infos.get(&glyph_id)
     }
 
+    /// Number of atlases, which are the layers of [`FontData::texture`].
     pub fn atlas_count(&self) -> usize {
         self.atlases.len()
     }
 
-    pub fn atlas(&self, code_point: char) -> Option<usize> {
-        self.code_point_to_atlas.get(&code_point).copied()
+    /// The atlas, i.e. texture layer, holding the glyph.
+    pub fn atlas(&self, glyph_id: GlyphId) -> Option<usize> {
+        self.glyph_locations.get(&glyph_id).map(|(atlas, _)| *atlas)
+    }
+
+    /// Rect of the glyph within its atlas in texels, without padding.
+    pub fn glyph_rect(&self, glyph_id: GlyphId) -> Option<URect> {
+        self.glyph_locations.get(&glyph_id).map(|(_, rect)| *rect)
     }
 
+    /// Texture coordinates of the glyph within its atlas.
     pub fn lookup_glyph(&self, glyph_id: GlyphId) -> Option<Rect> {
-        self.atlases
-            .iter()
-            .find_map(|atlas| atlas.lookup_glyph(glyph_id, self.range))
+        self.glyph_rect(glyph_id).map(|rect| {
+            let size_inv = 1f32 / self.layer_size.as_vec2();
+            let rect = rect.as_rect();
+            Rect::from_corners(rect.min * size_inv, rect.max * size_inv)
+        })
+    }
+
+    /// The texture array holding all atlases, one per layer.
+    pub fn texture(&self) -> Option<Handle<Image>> {
+        self.texture.clone()
+    }
+
+    /// Size of each layer of [`FontData::texture`] in texels.
+    pub fn layer_size(&self) -> UVec2 {
+        self.layer_size
     }
 
-    pub fn atlas_texture(&self, atlas: usize) -> Option<Handle<Image>> {
-        self.atlases
-            .get(atlas)
-            .map(|font_atlas| font_atlas.texture.clone())
+    /// Distance range in texels around the glyph outlines.
+    pub fn range(&self) -> u8 {
+        self.range
     }
 
     pub fn line_gap(&self) -> f32 {
         self.line_gap as f32
     }
+
+    pub fn mode(&self) -> SdfMode {
+        self.mode
+    }
+}
+
+/// Resizes a texture array, keeping the content of each layer in its top left corner.
+fn resize_layers(texture: &mut Image, size: Extent3d) {
+    let old_size = texture.texture_descriptor.size;
+    if (old_size.width, old_size.height) == (size.width, size.height) {
+        // layers are stored one after another
+        texture.resize(size);
+        return;
+    }
+    let pixel_size = texture.texture_descriptor.format.pixel_size();
+    let texel_count = size.width * size.height * size.depth_or_array_layers;
+    let mut data = vec![0; texel_count as usize * pixel_size];
+    let row_size = old_size.width.min(size.width) as usize * pixel_size;
+    for layer in 0..old_size
+        .depth_or_array_layers
+        .min(size.depth_or_array_layers)
+    {
+        for y in 0..old_size.height.min(size.height) {
+            let old_start = ((layer * old_size.height + y) * old_size.width) as usize * pixel_size;
+            let start = ((layer * size.height + y) * size.width) as usize * pixel_size;
+            data[start..start + row_size]
+                .copy_from_slice(&texture.data[old_start..old_start + row_size]);
+        }
+    }
+    texture.data = data;
+    texture.texture_descriptor.size = size;
+}
+
+/// When to evict generated glyphs without users from the [`FontAtlases`], the least recently
+/// used glyphs are evicted first. Nothing is evicted by default.
+#[derive(Debug, Clone, Default)]
+pub struct AtlasEviction {
+    /// Size of all atlas textures in bytes above which unused glyphs are evicted.
+    pub memory_budget: Option<usize>,
+    /// Number of unused glyphs kept per font.
+    pub max_unused_glyphs: Option<usize>,
 }
 
+// atlases are generated per font instance
+type FontKey = (AssetId<Font>, FontVariation);
+
 #[derive(Default, Resource)]
 pub struct FontAtlases {
-    font_data: HashMap<AssetId<Font>, FontData>,
+    font_data: HashMap<FontKey, FontData>,
+    // glyphs retained by each user
+    users: HashMap<Entity, Vec<(FontKey, GlyphId)>>,
+    tick: u64,
+    eviction: AtlasEviction,
+    // memory usage at which evicting did not free any layer, retried once usage grows
+    budget_stalled_at: Option<usize>,
+    growth: AtlasGrowth,
 }
 
 impl FontAtlases {
-    pub fn add_code_points<'c>(
+    pub fn with_eviction(mut self, eviction: AtlasEviction) -> Self {
+        self.eviction = eviction;
+        self
+    }
+
+    pub fn set_eviction(&mut self, eviction: AtlasEviction) {
+        self.eviction = eviction;
+    }
+
+    pub fn eviction(&self) -> &AtlasEviction {
+        &self.eviction
+    }
+
+    pub fn with_growth(mut self, growth: AtlasGrowth) -> Self {
+        self.growth = growth;
+        self
+    }
+
+    /// Sets the [`AtlasGrowth`] of all current and future fonts.
+    pub fn set_growth(&mut self, growth: AtlasGrowth) {
+        self.growth = growth;
+        for font_data in self.font_data.values_mut() {
+            font_data.set_growth(growth);
+        }
+    }
+
+    pub fn growth(&self) -> AtlasGrowth {
+        self.growth
+    }
+
+    /// Sets the glyphs used by `user`, releasing the ones it used before. Glyphs with users are
+    /// never evicted.
+    pub fn set_glyph_users(
         &mut self,
-        chars: &[char],
+        user: Entity,
+        glyphs: impl IntoIterator<Item = (AssetId<Font>, FontVariation, GlyphId)>,
+    ) {
+        // only glyphs that were requested can be retained, and later released
+        let retained: Vec<_> = glyphs
+            .into_iter()
+            .map(|(font_id, variation, glyph_id)| ((font_id, variation), glyph_id))
+            .filter(|(key, glyph_id)| {
+                self.font_data
+                    .get_mut(key)
+                    .filter(|font_data| font_data.has_glyph(*glyph_id))
+                    .map(|font_data| font_data.retain_glyph(*glyph_id))
+                    .is_some()
+            })
+            .collect();
+        self.release_glyph_users(user);
+        self.users.insert(user, retained);
+    }
+
+    /// Releases all glyphs used by `user`.
+    pub fn release_glyph_users(&mut self, user: Entity) {
+        self.tick += 1;
+        for (key, glyph_id) in self.users.remove(&user).unwrap_or_default() {
+            if let Some(font_data) = self.font_data.get_mut(&key) {
+                font_data.release_glyph(glyph_id, self.tick);
+            }
+        }
+    }
+
+    /// Size of all atlas textures in bytes.
+    pub fn memory_usage(&self) -> usize {
+        self.font_data.values().map(FontData::memory_usage).sum()
+    }
+
+    /// Evicts unused glyphs according to the [`AtlasEviction`] policy and returns the fonts
+    /// whose atlases were repacked.
+    pub fn evict_unused(&mut self, textures: &mut Assets<Image>) -> HashSet<AssetId<Font>> {
+        let mut evicted = HashSet::new();
+        if let Some(max_unused) = self.eviction.max_unused_glyphs {
+            for ((font_id, _), font_data) in self.font_data.iter_mut() {
+                let unused = font_data.unused_glyphs();
+                if unused.len() > max_unused {
+                    font_data.evict(&unused[..unused.len() - max_unused], textures);
+                    evicted.insert(*font_id);
+                }
+            }
+        }
+        if let Some(budget) = self.eviction.memory_budget {
+            let mut usage = self.memory_usage();
+            if usage <= budget
+                || self
+                    .budget_stalled_at
+                    .is_some_and(|stalled| usage <= stalled)
+            {
+                return evicted;
+            }
+            self.budget_stalled_at = None;
+            while usage > budget {
+                let mut unused: Vec<_> = self
+                    .font_data
+                    .iter()
+                    .flat_map(|(key, font_data)| {
+                        font_data.unused_glyphs().into_iter().map(|glyph_id| {
+                            let since = font_data.unused_since.get(&glyph_id).copied();
+                            (since.unwrap_or_default(), key.clone(), glyph_id)
+                        })
+                    })
+                    .collect();
+                if unused.is_empty() {
+                    break;
+                }
+                // repacking is expensive, evict the older half at once
+                unused.sort_by_key(|(since, _, _)| *since);
+                unused.truncate(unused.len().div_ceil(2));
+                let mut by_font: HashMap<_, Vec<_>> = HashMap::new();
+                for (_, key, glyph_id) in unused {
+                    by_font.entry(key).or_default().push(glyph_id);
+                }
+                for (key, glyph_ids) in by_font {
+                    if let Some(font_data) = self.font_data.get_mut(&key) {
+                        font_data.evict(&glyph_ids, textures);
+                        evicted.insert(key.0);
+                    }
+                }
+                // memory is only freed once trailing layers become empty
+                let previous = std::mem::replace(&mut usage, self.memory_usage());
+                if usage >= previous {
+                    log::warn!(
+                        "Atlas memory budget of {budget} bytes can't be met, {usage} bytes are \
+                        used by glyphs still in use"
+                    );
+                    self.budget_stalled_at = Some(usage);
+                    break;
+                }
+            }
+        }
+        evicted
+    }
+
+    /// Removes all atlases of a font, e.g. after the font asset was unloaded or modified.
+    pub fn remove_font(&mut self, font_id: AssetId<Font>, textures: &mut Assets<Image>) {
+        self.font_data.retain(|(id, _), font_data| {
+            if *id == font_id {
+                if let Some(texture) = &font_data.texture {
+                    textures.remove(texture);
+                }
+            }
+            *id != font_id
+        });
+    }
+
+    /// Requests generation of the glyphs, see [`FontData::request_glyph`].
+    pub fn add_glyphs(
+        &mut self,
+        glyph_ids: &[GlyphId],
         font_id: AssetId<Font>,
+        variation: &FontVariation,
+        fonts: &Assets<Font>,
+    ) {
+        let Some(font) = fonts.get(font_id) else {
+            bevy::log::error!("Font not found!");
+            return;
+        };
+        let font = font.instance(variation);
+        let font_data = self
+            .font_data
+            .entry((font_id, variation.clone()))
+            .or_insert_with(|| {
+                bevy::log::info!("Inserting new FontData entry.");
+                FontData::from(&font).with_growth(self.growth)
+            });
+        for glyph_id in glyph_ids {
+            if !font_data.has_glyph(*glyph_id) {
+                font_data.request_glyph(*glyph_id, &font);
+            }
+        }
+        // glyphs that are never used are evicted after the ones used before
+        self.tick += 1;
+        font_data.track_unused(glyph_ids, self.tick);
+    }
+
+    /// Adds all finished glyphs to their atlases and returns the fonts that received new glyphs
+    /// or whose glyphs moved when growing an atlas.
+    pub fn poll_pending(&mut self, textures: &mut Assets<Image>) -> HashSet<AssetId<Font>> {
+        self.font_data
+            .iter_mut()
+            .filter_map(|((font_id, _), font_data)| {
+                let added = font_data.pending_count() > 0 && font_data.poll_pending(textures);
+                let repacked = std::mem::take(&mut font_data.repacked);
+                (added || repacked).then_some(*font_id)
+            })
+            .collect()
+    }
+
+    /// Generates the glyphs of all `characters` up front, see [`FontData::prewarm`].
+    pub fn prewarm(
+        &mut self,
+        characters: &CharacterSet,
+        font_id: AssetId<Font>,
+        variation: &FontVariation,
         fonts: &Assets<Font>,
         textures: &mut Assets<Image>,
-        // materials: &mut Assets<SdfMaterial>,
+    ) -> usize {
+        let Some(font) = fonts.get(font_id) else {
+            bevy::log::error!("Font not found!");
+            return 0;
+        };
+        let font = font.instance(variation);
+        let glyph_ids: Vec<_> = characters
+            .chars()
+            .into_iter()
+            .filter_map(|c| font.glyph_id(c))
+            .collect();
+        let font_data = self
+            .font_data
+            .entry((font_id, variation.clone()))
+            .or_insert_with(|| FontData::from(&font).with_growth(self.growth));
+        let added = font_data.prewarm(&glyph_ids, &font, textures);
+        self.tick += 1;
+        font_data.track_unused(&glyph_ids, self.tick);
+        added
+    }
+
+    pub fn add_code_points(
+        &mut self,
+        chars: &[char],
+        font_id: AssetId<Font>,
+        variation: &FontVariation,
+        fonts: &Assets<Font>,
     ) {
         let Some(font) = fonts.get(font_id) else {
             bevy::log::error!("Font not found!");
             return;
         };
-        let font_data = self.font_data.entry(font_id).or_insert_with(|| {
-            bevy::log::info!("Inserting new FontData entry.");
-            FontData::from(font)
-        });
-        for c in chars {
-            if !font_data.has_glyph(*c) {
-                if let Some(i) = font_data.add_glyph(*c, font, textures) {
-                    bevy::log::info!("Code point {c} added to {i}!");
+        let glyph_ids: Vec<_> = chars
+            .iter()
+            .filter_map(|c| {
+                let glyph_id = font.glyph_id(*c);
+                if glyph_id.is_none() {
+                    log::warn!("No glyph generated for {c}. No glyph data available");
                 }
+                glyph_id
+            })
+            .collect();
+        self.add_glyphs(&glyph_ids, font_id, variation, fonts);
+    }
+
+    /// Adds the glyphs of a loaded [`BakedFontAtlas`] to the atlases of its font, so they are
+    /// not generated at runtime.
+    pub fn add_baked(&mut self, baked: &BakedFontAtlas, textures: &mut Assets<Image>) {
+        self.font_data
+            .entry((baked.font.id(), baked.variation.clone()))
+            .and_modify(|font_data| font_data.add_baked(baked, textures))
+            .or_insert_with(|| FontData::from_baked(baked, textures).with_growth(self.growth));
+    }
+
+    pub fn data(&self, font_id: AssetId<Font>, variation: &FontVariation) -> Option<&FontData> {
+        self.font_data.get(&(font_id, variation.clone()))
+    }
+}
+
+/// Frees the atlases of unloaded fonts and of modified ones, which are generated again when
+/// requested.
+pub(super) fn remove_stale_fonts(
+    mut events: EventReader<AssetEvent<Font>>,
+    mut font_atlases: ResMut<FontAtlases>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Removed { id } | AssetEvent::Modified { id } = event {
+            font_atlases.remove_font(*id, &mut textures);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::TextureFormat;
+    use bevy::tasks::TaskPool;
+
+    use super::*;
+    use crate::font::test_font::{self, A, B, C};
+
+    struct Fixture {
+        atlases: FontAtlases,
+        fonts: Assets<Font>,
+        textures: Assets<Image>,
+        font_id: AssetId<Font>,
+    }
+
+    impl Fixture {
+        fn new(eviction: AtlasEviction) -> Self {
+            ComputeTaskPool::get_or_init(TaskPool::default);
+            let mut fonts = Assets::default();
+            let font_id = fonts.add(test_font::font()).id();
+            Self {
+                atlases: FontAtlases::default().with_eviction(eviction),
+                fonts,
+                textures: Assets::default(),
+                font_id,
             }
         }
+
+        fn prewarm(&mut self, text: &str) {
+            self.atlases.prewarm(
+                &CharacterSet::Text(text.into()),
+                self.font_id,
+                &FontVariation::default(),
+                &self.fonts,
+                &mut self.textures,
+            );
+        }
+
+        fn use_glyphs(&mut self, user: u32, glyph_ids: &[GlyphId]) {
+            let glyphs = glyph_ids
+                .iter()
+                .map(|glyph_id| (self.font_id, FontVariation::default(), *glyph_id));
+            self.atlases.set_glyph_users(Entity::from_raw(user), glyphs);
+        }
+
+        fn release(&mut self, user: u32) {
+            self.atlases.release_glyph_users(Entity::from_raw(user));
+        }
+
+        fn evict(&mut self) -> HashSet<AssetId<Font>> {
+            self.atlases.evict_unused(&mut self.textures)
+        }
+
+        fn data(&self) -> &FontData {
+            self.atlases
+                .data(self.font_id, &FontVariation::default())
+                .unwrap()
+        }
+
+        /// Texels of a packed glyph.
+        fn texels(&self, glyph_id: GlyphId) -> Vec<u8> {
+            let data = self.data();
+            let (layer, rect) = data.glyph_locations[&glyph_id];
+            let texture = self.textures.get(&data.texture().unwrap()).unwrap();
+            read_rect(texture, layer, rect)
+        }
     }
 
-    pub fn data(&self, font_id: AssetId<Font>) -> Option<&FontData> {
-        self.font_data.get(&font_id)
+    #[test]
+    fn evicts_least_recently_used_glyphs_before_prewarmed_ones() {
+        let mut fixture = Fixture::new(AtlasEviction {
+            max_unused_glyphs: Some(2),
+            ..Default::default()
+        });
+        fixture.prewarm("ab");
+        fixture.use_glyphs(1, &[A]);
+        fixture.use_glyphs(2, &[B]);
+        fixture.release(1);
+        fixture.prewarm("c");
+        fixture.release(2);
+
+        let b_texels = fixture.texels(B);
+        assert_eq!(fixture.evict(), HashSet::from([fixture.font_id]));
+        let data = fixture.data();
+        assert!(!data.has_glyph(A));
+        assert!(data.has_glyph(B) && data.has_glyph(C));
+        // the remaining glyphs were repacked with their distance fields
+        assert_eq!(fixture.texels(B), b_texels);
+        assert!(fixture.evict().is_empty());
+    }
+
+    #[test]
+    fn keeps_glyphs_in_use() {
+        let mut fixture = Fixture::new(AtlasEviction {
+            max_unused_glyphs: Some(0),
+            ..Default::default()
+        });
+        fixture.prewarm("abc");
+        fixture.use_glyphs(1, &[A, C]);
+        fixture.use_glyphs(2, &[C]);
+        fixture.release(2);
+        fixture.evict();
+        let data = fixture.data();
+        assert!(data.has_glyph(A) && data.has_glyph(C));
+        assert!(!data.has_glyph(B));
+        assert_eq!(data.use_count(C), 1);
+    }
+
+    #[test]
+    fn stops_evicting_when_the_memory_budget_cant_be_met() {
+        let mut fixture = Fixture::new(AtlasEviction {
+            memory_budget: Some(1),
+            ..Default::default()
+        });
+        fixture.prewarm("ab");
+        fixture.prewarm("c");
+        fixture.use_glyphs(1, &[A]);
+        let usage = fixture.atlases.memory_usage();
+
+        // the only layer is never freed, evicting the older unused glyph frees no memory
+        assert_eq!(fixture.evict(), HashSet::from([fixture.font_id]));
+        assert_eq!(fixture.atlases.memory_usage(), usage);
+        assert_eq!(fixture.atlases.budget_stalled_at, Some(usage));
+        let data = fixture.data();
+        assert!(data.has_glyph(A));
+        assert_eq!(
+            [B, C].map(|glyph_id| data.has_glyph(glyph_id)),
+            [false, true]
+        );
+
+        // no further evictions until the usage grows
+        assert!(fixture.evict().is_empty());
+        assert!(fixture.data().has_glyph(C));
+    }
+
+    fn texture(width: u32, height: u32, layers: u32, format: TextureFormat) -> Image {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        };
+        let pixel = vec![0; format.pixel_size()];
+        Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &pixel,
+            format,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    /// Distinct texels for a rect of the given texel count and format.
+    fn texels(count: u32, format: TextureFormat, seed: u8) -> Vec<u8> {
+        (0..count as usize * format.pixel_size())
+            .map(|i| seed.wrapping_add(i as u8) | 1)
+            .collect()
+    }
+
+    #[test]
+    fn double_growth_starts_small_and_keeps_glyphs_in_place() {
+        let mut fixture = Fixture::new(AtlasEviction::default());
+        fixture
+            .atlases
+            .set_growth(AtlasGrowth::Double { max_size: 512 });
+        // two glyphs don't fit side by side or stacked into the first layer
+        let font = test_font::font().with_pixels_per_em(330.0);
+        fixture.font_id = fixture.fonts.add(font).id();
+
+        fixture.prewarm("a");
+        assert_eq!(fixture.data().layer_size(), UVec2::splat(256));
+        let rect = fixture.data().glyph_rect(A).unwrap();
+        let texels = fixture.texels(A);
+        fixture.atlases.poll_pending(&mut fixture.textures);
+
+        fixture.prewarm("bc");
+        let data = fixture.data();
+        assert_eq!(data.layer_size(), UVec2::splat(512));
+        assert_eq!(data.atlas_count(), 1);
+        // the glyph keeps its texels, its texture coordinates change with the layer size
+        assert_eq!(data.glyph_rect(A), Some(rect));
+        assert_eq!(fixture.texels(A), texels);
+        let repacked = fixture.atlases.poll_pending(&mut fixture.textures);
+        assert_eq!(repacked, HashSet::from([fixture.font_id]));
+        assert!(fixture
+            .atlases
+            .poll_pending(&mut fixture.textures)
+            .is_empty());
+    }
+
+    #[test]
+    fn rects_round_trip_within_their_layer() {
+        for format in [TextureFormat::R8Unorm, TextureFormat::Rgba8Unorm] {
+            let mut texture = texture(8, 4, 2, format);
+            let rect = URect::new(2, 1, 5, 3);
+            let data = texels(6, format, 10);
+            write_rect(&mut texture, 1, rect, &data);
+
+            assert_eq!(read_rect(&texture, 1, rect), data);
+            assert!(read_rect(&texture, 0, rect).iter().all(|&texel| texel == 0));
+            let layer = URect::new(0, 0, 8, 4);
+            let written = read_rect(&texture, 1, layer)
+                .iter()
+                .filter(|&&texel| texel != 0)
+                .count();
+            assert_eq!(written, data.len());
+        }
+    }
+
+    #[test]
+    fn resizing_keeps_the_content_of_each_layer() {
+        for format in [TextureFormat::R8Unorm, TextureFormat::Rgba8Unorm] {
+            let mut texture = texture(4, 4, 2, format);
+            let layer = URect::new(0, 0, 4, 4);
+            let first = texels(16, format, 20);
+            let second = texels(16, format, 60);
+            write_rect(&mut texture, 0, layer, &first);
+            write_rect(&mut texture, 1, layer, &second);
+
+            // more layers of the same size
+            resize_layers(
+                &mut texture,
+                Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 3,
+                },
+            );
+            assert_eq!(read_rect(&texture, 0, layer), first);
+            assert_eq!(read_rect(&texture, 1, layer), second);
+            assert!(read_rect(&texture, 2, layer)
+                .iter()
+                .all(|&texel| texel == 0));
+
+            // larger layers
+            let size = Extent3d {
+                width: 8,
+                height: 6,
+                depth_or_array_layers: 3,
+            };
+            resize_layers(&mut texture, size);
+            assert_eq!(texture.texture_descriptor.size, size);
+            assert_eq!(texture.data.len(), 8 * 6 * 3 * format.pixel_size());
+            assert_eq!(read_rect(&texture, 0, layer), first);
+            assert_eq!(read_rect(&texture, 1, layer), second);
+            let grown = URect::new(0, 0, 8, 6);
+            for layer in 0..3 {
+                let written = read_rect(&texture, layer, grown)
+                    .iter()
+                    .filter(|&&texel| texel != 0)
+                    .count();
+                assert_eq!(written, if layer < 2 { first.len() } else { 0 });
+            }
+        }
     }
 }
@@ -0,0 +1,452 @@
+use bevy::{
+    asset::{
+        io::Reader, Asset, AssetEvent, AssetLoader, Assets, Handle, LoadContext, LoadDirectError,
+        ParseAssetPathError, ReadAssetBytesError,
+    },
+    math::{URect, UVec2, Vec2},
+    prelude::{EventReader, Res, ResMut},
+    reflect::TypePath,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+        texture::{Image, TextureFormatPixelInfo},
+    },
+    utils::ConditionalSendFuture,
+};
+use image::{DynamicImage, GrayImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+use super::atlas::{FontAtlases, FontData};
+use super::font::{Font, FontVariation, GlyphId, GlyphInfo, SdfMode};
+use super::loader::FontLoaderSettings;
+
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum BakedAtlasError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    ReadAssetBytes(#[from] ReadAssetBytesError),
+    #[error(transparent)]
+    AssetPath(#[from] ParseAssetPathError),
+    #[error(transparent)]
+    Font(#[from] Box<LoadDirectError>),
+    /// The texture of an atlas page is not available for baking.
+    #[error("atlas page {0} has no CPU side texture")]
+    MissingPage(usize),
+    /// Atlases produced by msdf-atlas-gen need [`BakedAtlasLoaderSettings::font`].
+    #[error("no font given for the msdf-atlas-gen atlas")]
+    MissingFont,
+    #[error("unsupported msdf-atlas-gen atlas type {0:?}")]
+    UnsupportedType(String),
+}
+
+/// A glyph of a baked atlas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedGlyph {
+    pub id: u16,
+    pub page: usize,
+    /// `[min_x, min_y, max_x, max_y]` of the glyph outline in texels, without padding, with the
+    /// origin at the bottom left of the page image.
+    pub rect: [u32; 4],
+    pub advance: [f32; 2],
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl BakedGlyph {
+    pub fn id(&self) -> GlyphId {
+        GlyphId(self.id)
+    }
+
+    pub fn rect(&self) -> URect {
+        let [min_x, min_y, max_x, max_y] = self.rect;
+        URect::new(min_x, min_y, max_x, max_y)
+    }
+
+    pub fn info(&self) -> GlyphInfo {
+        GlyphInfo {
+            id: self.id(),
+            advance: self.advance.into(),
+            offset: self.offset.into(),
+            size: self.size.into(),
+        }
+    }
+}
+
+/// The file format of baked atlases (`.atlas.json`), stored next to one PNG image per page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BakedAtlasFile {
+    /// Path of the font, relative to the atlas file.
+    pub font: String,
+    #[serde(default)]
+    pub variation: FontVariation,
+    pub mode: SdfMode,
+    pub range: u8,
+    pub line_gap: f64,
+    /// Paths of the page images, relative to the atlas file.
+    pub pages: Vec<String>,
+    pub glyphs: Vec<BakedGlyph>,
+}
+
+/// Pre-generated glyphs of a font, added to the [`FontAtlases`] once loaded.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct BakedFontAtlas {
+    #[dependency]
+    pub font: Handle<Font>,
+    pub variation: FontVariation,
+    pub mode: SdfMode,
+    pub range: u8,
+    pub line_gap: f64,
+    #[dependency]
+    pub pages: Vec<Handle<Image>>,
+    pub page_sizes: Vec<UVec2>,
+    pub glyphs: Vec<BakedGlyph>,
+}
+
+impl FontData {
+    /// Writes the glyphs generated so far to the atlas file at `path`, with the pages as PNG
+    /// images next to it. `font` is the path of the font relative to the atlas file.
+    ///
+    /// Glyphs still being generated are not included.
+    pub fn bake(
+        &self,
+        path: impl AsRef<Path>,
+        font: impl Into<String>,
+        variation: &FontVariation,
+        textures: &Assets<Image>,
+    ) -> Result<(), BakedAtlasError> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let stem = file_name.strip_suffix(".atlas.json").unwrap_or(file_name);
+
+        let texture = self.texture().and_then(|texture| textures.get(&texture));
+        let size = self.layer_size();
+        let layer_bytes = size.element_product() as usize * self.mode.texture_format().pixel_size();
+        let mut pages = Vec::with_capacity(self.atlases.len());
+        for index in 0..self.atlases.len() {
+            let page: DynamicImage = texture
+                .and_then(|texture| {
+                    let layer = texture
+                        .data
+                        .get(index * layer_bytes..(index + 1) * layer_bytes)?
+                        .to_vec();
+                    // only store the channels in use, so the pages are visible in image viewers
+                    Some(match self.mode {
+                        SdfMode::Sdf => GrayImage::from_raw(size.x, size.y, layer)?.into(),
+                        SdfMode::Msdf => {
+                            DynamicImage::from(RgbaImage::from_raw(size.x, size.y, layer)?)
+                                .to_rgb8()
+                                .into()
+                        }
+                        SdfMode::Mtsdf => RgbaImage::from_raw(size.x, size.y, layer)?.into(),
+                    })
+                })
+                .ok_or(BakedAtlasError::MissingPage(index))?;
+            // glyphs are stored upside down in the texture
+            let page = page.flipv();
+            let page_name = format!("{stem}.{index}.png");
+            page.save(path.with_file_name(&page_name))?;
+            pages.push(page_name);
+        }
+
+        let mut glyphs: Vec<_> = self
+            .glyph_locations
+            .iter()
+            .filter_map(|(glyph_id, (page, rect))| {
+                let info = self.glyph_infos.get(glyph_id)?;
+                Some(BakedGlyph {
+                    id: glyph_id.0,
+                    page: *page,
+                    rect: [rect.min.x, rect.min.y, rect.max.x, rect.max.y],
+                    advance: info.advance.into(),
+                    offset: info.offset.into(),
+                    size: info.size.into(),
+                })
+            })
+            .collect();
+        glyphs.sort_by_key(|glyph| glyph.id);
+
+        let file = BakedAtlasFile {
+            font: font.into(),
+            variation: variation.clone(),
+            mode: self.mode,
+            range: self.range,
+            line_gap: self.line_gap,
+            pages,
+            glyphs,
+        };
+        std::fs::write(path, serde_json::to_vec_pretty(&file)?)?;
+        Ok(())
+    }
+}
+
+/// JSON layout written by msdf-atlas-gen (`-json`), only the parts used here.
+#[derive(Deserialize)]
+struct MsdfAtlasGenFile {
+    atlas: MsdfAtlasGenAtlas,
+    metrics: MsdfAtlasGenMetrics,
+    glyphs: Vec<MsdfAtlasGenGlyph>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfAtlasGenAtlas {
+    #[serde(rename = "type")]
+    kind: String,
+    distance_range: f64,
+    size: f32,
+    height: u32,
+    #[serde(default)]
+    y_origin: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfAtlasGenMetrics {
+    #[serde(default = "default_em_size")]
+    em_size: f32,
+    line_height: f64,
+}
+
+fn default_em_size() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfAtlasGenGlyph {
+    unicode: Option<u32>,
+    index: Option<u16>,
+    plane_bounds: Option<MsdfAtlasGenBounds>,
+    atlas_bounds: Option<MsdfAtlasGenBounds>,
+}
+
+#[derive(Deserialize)]
+struct MsdfAtlasGenBounds {
+    left: f32,
+    bottom: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AtlasFile {
+    Baked(BakedAtlasFile),
+    MsdfAtlasGen(MsdfAtlasGenFile),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BakedAtlasLoaderSettings {
+    /// Font of an atlas produced by msdf-atlas-gen, relative to the atlas file.
+    pub font: Option<String>,
+    /// Image of an atlas produced by msdf-atlas-gen, relative to the atlas file. Defaults to
+    /// the atlas file name with a `.png` extension.
+    pub image: Option<String>,
+}
+
+/// Loads `.atlas.json` files, either written by [`FontData::bake`] or produced by
+/// msdf-atlas-gen.
+#[derive(Default)]
+pub struct BakedAtlasLoader;
+
+impl AssetLoader for BakedAtlasLoader {
+    type Asset = BakedFontAtlas;
+    type Settings = BakedAtlasLoaderSettings;
+    type Error = BakedAtlasError;
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        settings: &'a BakedAtlasLoaderSettings,
+        load_context: &'a mut LoadContext,
+    ) -> impl ConditionalSendFuture<Output = Result<BakedFontAtlas, Self::Error>> {
+        async move {
+            let mut bytes = Vec::new();
+            bevy::asset::AsyncReadExt::read_to_end(reader, &mut bytes).await?;
+            match serde_json::from_slice(&bytes)? {
+                AtlasFile::Baked(file) => load_baked(file, load_context).await,
+                AtlasFile::MsdfAtlasGen(file) => {
+                    load_msdf_atlas_gen(file, settings, load_context).await
+                }
+            }
+        }
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["atlas.json"]
+    }
+}
+
+async fn load_baked(
+    file: BakedAtlasFile,
+    load_context: &mut LoadContext<'_>,
+) -> Result<BakedFontAtlas, BakedAtlasError> {
+    let mut pages = Vec::with_capacity(file.pages.len());
+    let mut page_sizes = Vec::with_capacity(file.pages.len());
+    for (index, path) in file.pages.iter().enumerate() {
+        let page = load_page(path, file.mode, load_context).await?;
+        page_sizes.push(UVec2::new(page.width(), page.height()));
+        pages.push(
+            load_context.add_labeled_asset(format!("page{index}"), page_image(page, file.mode)),
+        );
+    }
+    let font = load_context.asset_path().resolve_embed(&file.font)?;
+    Ok(BakedFontAtlas {
+        font: load_context.load(font),
+        variation: file.variation,
+        mode: file.mode,
+        range: file.range,
+        line_gap: file.line_gap,
+        pages,
+        page_sizes,
+        glyphs: file.glyphs,
+    })
+}
+
+async fn load_msdf_atlas_gen(
+    file: MsdfAtlasGenFile,
+    settings: &BakedAtlasLoaderSettings,
+    load_context: &mut LoadContext<'_>,
+) -> Result<BakedFontAtlas, BakedAtlasError> {
+    let mode = match file.atlas.kind.as_str() {
+        "sdf" | "psdf" => SdfMode::Sdf,
+        "msdf" => SdfMode::Msdf,
+        "mtsdf" => SdfMode::Mtsdf,
+        _ => return Err(BakedAtlasError::UnsupportedType(file.atlas.kind)),
+    };
+    let font_path = settings.font.as_ref().ok_or(BakedAtlasError::MissingFont)?;
+    let font_path = load_context.asset_path().resolve_embed(font_path)?;
+    // generated glyphs must match the channels and range of the atlas
+    let range = file.atlas.distance_range.round() as u8;
+    let font_settings = move |settings: &mut FontLoaderSettings| {
+        settings.mode = mode;
+        settings.range = range;
+    };
+    // the font is needed to map code points to glyphs and for the outline bounds
+    let font = load_context
+        .loader()
+        .with_settings(font_settings)
+        .direct()
+        .load::<Font>(font_path.clone())
+        .await
+        .map_err(Box::new)?
+        .take();
+
+    let image_path = match &settings.image {
+        Some(image) => image.clone(),
+        None => {
+            let file_name = load_context
+                .path()
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let stem = file_name.strip_suffix(".atlas.json").unwrap_or(file_name);
+            format!("{stem}.png")
+        }
+    };
+    let page = load_page(&image_path, mode, load_context).await?;
+    let page_size = UVec2::new(page.width(), page.height());
+
+    let y_from_top = file.atlas.y_origin.as_deref() == Some("top");
+    let scale = file.atlas.size;
+    let glyphs = file
+        .glyphs
+        .iter()
+        .filter_map(|glyph| {
+            let id = glyph.index.map(GlyphId).or_else(|| {
+                char::from_u32(glyph.unicode?).and_then(|code_point| font.glyph_id(code_point))
+            })?;
+            let (plane, atlas) = (glyph.plane_bounds.as_ref()?, glyph.atlas_bounds.as_ref()?);
+            let atlas_bottom = if y_from_top {
+                file.atlas.height as f32 - atlas.bottom
+            } else {
+                atlas.bottom
+            };
+            // the bounds include the distance range, map the outline bounds into the atlas
+            let info = font.glyph_info(id);
+            let plane_min = Vec2::new(plane.left, plane.bottom) / file.metrics.em_size;
+            let min = Vec2::new(atlas.left, atlas_bottom) + (info.offset - plane_min) * scale;
+            let max = min + info.size * scale;
+            Some(BakedGlyph {
+                id: id.0,
+                page: 0,
+                rect: [
+                    min.x.round().max(0.0) as u32,
+                    min.y.round().max(0.0) as u32,
+                    max.x.round().max(0.0) as u32,
+                    max.y.round().max(0.0) as u32,
+                ],
+                advance: info.advance.into(),
+                offset: info.offset.into(),
+                size: info.size.into(),
+            })
+        })
+        .collect();
+
+    Ok(BakedFontAtlas {
+        font: load_context
+            .loader()
+            .with_settings(font_settings)
+            .load(font_path),
+        variation: font.variation().clone(),
+        mode,
+        range,
+        line_gap: file.metrics.line_height / file.metrics.em_size as f64,
+        pages: vec![load_context.add_labeled_asset("page0".to_owned(), page_image(page, mode))],
+        page_sizes: vec![page_size],
+        glyphs,
+    })
+}
+
+/// Loads a page image relative to the atlas file, flipped and converted to match the glyph
+/// orientation and channels of generated atlases.
+async fn load_page(
+    path: &str,
+    mode: SdfMode,
+    load_context: &mut LoadContext<'_>,
+) -> Result<DynamicImage, BakedAtlasError> {
+    let path = load_context.asset_path().resolve_embed(path)?;
+    let bytes = load_context.read_asset_bytes(path).await?;
+    let page = image::load_from_memory(&bytes)?.flipv();
+    Ok(match mode {
+        SdfMode::Sdf => page.into_luma8().into(),
+        SdfMode::Msdf | SdfMode::Mtsdf => page.into_rgba8().into(),
+    })
+}
+
+fn page_image(page: DynamicImage, mode: SdfMode) -> Image {
+    Image::new(
+        Extent3d {
+            width: page.width(),
+            height: page.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        page.into_bytes(),
+        mode.texture_format(),
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+pub(super) fn add_baked_atlases(
+    mut events: EventReader<AssetEvent<BakedFontAtlas>>,
+    baked_atlases: Res<Assets<BakedFontAtlas>>,
+    mut font_atlases: ResMut<FontAtlases>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::LoadedWithDependencies { id } = event {
+            if let Some(baked) = baked_atlases.get(*id) {
+                font_atlases.add_baked(baked, &mut textures);
+            }
+        }
+    }
+}
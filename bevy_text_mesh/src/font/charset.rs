@@ -0,0 +1,37 @@
+use std::ops::RangeInclusive;
+
+/// A set of code points, e.g. for prewarming font atlases.
+#[derive(Debug, Clone)]
+pub enum CharacterSet {
+    /// Printable ASCII characters (`' '..='~'`).
+    Ascii,
+    /// Printable characters of ISO 8859-1, including ASCII.
+    Latin1,
+    /// All characters of a string.
+    Text(String),
+    /// A range of code points.
+    Range(RangeInclusive<char>),
+}
+
+impl CharacterSet {
+    pub fn chars(&self) -> Vec<char> {
+        match self {
+            Self::Ascii => (' '..='~').collect(),
+            Self::Latin1 => (' '..='~').chain('\u{a0}'..='\u{ff}').collect(),
+            Self::Text(text) => text.chars().filter(|c| !c.is_control()).collect(),
+            Self::Range(range) => range.clone().collect(),
+        }
+    }
+}
+
+impl From<&str> for CharacterSet {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_owned())
+    }
+}
+
+impl From<RangeInclusive<char>> for CharacterSet {
+    fn from(range: RangeInclusive<char>) -> Self {
+        Self::Range(range)
+    }
+}
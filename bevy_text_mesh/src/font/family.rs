@@ -0,0 +1,25 @@
+use bevy::asset::Handle;
+
+use super::font::Font;
+
+/// An ordered list of fonts, where code points missing from a font are taken from the next one.
+///
+/// The fonts are copied into a [`TextMesh`](crate::TextMesh) when it is created, a family
+/// always starts with its primary font.
+#[derive(Clone, Debug)]
+pub struct FontFamily {
+    pub fonts: Vec<Handle<Font>>,
+}
+
+impl FontFamily {
+    pub fn new(primary: Handle<Font>) -> Self {
+        Self {
+            fonts: vec![primary],
+        }
+    }
+
+    pub fn with_fallback(mut self, font: Handle<Font>) -> Self {
+        self.fonts.push(font);
+        self
+    }
+}
@@ -1,4 +1,8 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
 use bevy::{
     math::Vec2,
@@ -14,9 +18,14 @@ use fdsm::{
     shape::{Contour, Shape},
     transform::Transform,
 };
-use image::{GrayImage, RgbaImage};
+use image::{GrayImage, RgbImage, RgbaImage};
 use nalgebra::{Affine2, Similarity2, Vector2};
-use owned_ttf_parser::{AsFaceRef, OutlineBuilder, Rect};
+use owned_ttf_parser::{
+    gpos::{PairAdjustment, PositioningSubtable},
+    opentype_layout::{Feature, LayoutTable},
+    AsFaceRef, OutlineBuilder, Rect, Tag, VariationAxis,
+};
+use serde::{Deserialize, Serialize};
 
 pub use owned_ttf_parser::GlyphId;
 
@@ -28,24 +37,265 @@ pub struct GlyphInfo {
     pub size: Vec2,
 }
 
+/// The kind of distance field generated for each glyph.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SdfMode {
+    /// Single-channel signed distance field, stored in the red channel.
+    #[default]
+    Sdf,
+    /// Multi-channel signed distance field in the RGB channels, which keeps corners sharp.
+    Msdf,
+    /// Multi-channel signed distance field in RGB plus the true distance in alpha.
+    Mtsdf,
+}
+
+impl SdfMode {
+    /// Format of the glyph and atlas textures. There is no three channel format, MSDF leaves
+    /// the alpha channel unused.
+    pub fn texture_format(&self) -> TextureFormat {
+        match self {
+            SdfMode::Sdf => TextureFormat::R8Unorm,
+            SdfMode::Msdf | SdfMode::Mtsdf => TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// Coordinates on the axes of a variable font, e.g. `wght` for the weight.
+///
+/// Axes that are not set keep their default value. Serialized as a map from axis tag to
+/// coordinate, e.g. `{"wght": 700.0}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "BTreeMap<String, f32>", into = "BTreeMap<String, f32>")]
+pub struct FontVariation {
+    // sorted by axis, so equal variations hash equally
+    coordinates: Vec<([u8; 4], f32)>,
+}
+
+impl FontVariation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the coordinate of `axis`, replacing any previous value.
+    pub fn with(mut self, axis: &[u8; 4], value: f32) -> Self {
+        match self
+            .coordinates
+            .binary_search_by(|(existing, _)| existing.cmp(axis))
+        {
+            Ok(index) => self.coordinates[index].1 = value,
+            Err(index) => self.coordinates.insert(index, (*axis, value)),
+        }
+        self
+    }
+
+    pub fn weight(value: f32) -> Self {
+        Self::new().with(b"wght", value)
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.coordinates.is_empty()
+    }
+
+    /// Combines both variations, coordinates of `other` take precedence.
+    pub fn merged(&self, other: &FontVariation) -> FontVariation {
+        other
+            .coordinates
+            .iter()
+            .fold(self.clone(), |variation, (axis, value)| {
+                variation.with(axis, *value)
+            })
+    }
+}
+
+impl TryFrom<BTreeMap<String, f32>> for FontVariation {
+    type Error = String;
+
+    fn try_from(coordinates: BTreeMap<String, f32>) -> Result<Self, Self::Error> {
+        coordinates
+            .into_iter()
+            .try_fold(Self::new(), |variation, (axis, value)| {
+                let tag: [u8; 4] = axis
+                    .as_bytes()
+                    .try_into()
+                    .map_err(|_| format!("axis tag {axis:?} is not 4 bytes long"))?;
+                Ok(variation.with(&tag, value))
+            })
+    }
+}
+
+impl From<FontVariation> for BTreeMap<String, f32> {
+    fn from(variation: FontVariation) -> Self {
+        variation
+            .coordinates
+            .into_iter()
+            .map(|(axis, value)| (String::from_utf8_lossy(&axis).into_owned(), value))
+            .collect()
+    }
+}
+
+impl PartialEq for FontVariation {
+    fn eq(&self, other: &Self) -> bool {
+        self.coordinates.len() == other.coordinates.len()
+            && self
+                .coordinates
+                .iter()
+                .zip(&other.coordinates)
+                .all(|(a, b)| a.0 == b.0 && a.1.to_bits() == b.1.to_bits())
+    }
+}
+
+impl Eq for FontVariation {}
+
+impl Hash for FontVariation {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for (axis, value) in self.coordinates.iter() {
+            axis.hash(state);
+            value.to_bits().hash(state);
+        }
+    }
+}
+
+/// The font data a face is parsed from.
+#[derive(Clone)]
+enum FaceData {
+    Owned(Arc<owned_ttf_parser::OwnedFace>),
+    /// A face of a collection, all faces share the collection data.
+    Collection(Arc<CollectionFace>),
+}
+
+/// A face parsed from the shared data of a font collection, like an
+/// [`OwnedFace`](owned_ttf_parser::OwnedFace) without its own copy of the data.
+struct CollectionFace {
+    // borrows from `_data`, declared first so it is dropped before the data
+    face: owned_ttf_parser::Face<'static>,
+    _data: Arc<[u8]>,
+}
+
+impl CollectionFace {
+    fn parse(data: Arc<[u8]>, index: u32) -> Result<Self, owned_ttf_parser::FaceParsingError> {
+        // SAFETY: the slice points into the allocation of `data`, which is never mutated and
+        // lives as long as `self` holds its reference. The face is only handed out through
+        // `CollectionFace::face`, which limits its lifetime to the one of `self`.
+        let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) };
+        let face = owned_ttf_parser::Face::parse(bytes, index)?;
+        Ok(Self { face, _data: data })
+    }
+
+    fn face(&self) -> &owned_ttf_parser::Face<'_> {
+        &self.face
+    }
+}
+
 #[derive(Asset, TypePath, Clone)]
 pub struct Font {
-    // TODO: parse on-demand instead of storing a owned ttf-Face?
-    face: Arc<owned_ttf_parser::OwnedFace>,
+    face: FaceData,
+    mode: SdfMode,
+    pixels_per_em: f64,
+    range: u8,
+    variation: FontVariation,
 }
 
 impl Font {
     pub fn from(face: owned_ttf_parser::OwnedFace) -> Self {
+        Self::from_data(FaceData::Owned(Arc::new(face)))
+    }
+
+    /// Creates the font of face `index` of a font collection, sharing `data` with the other
+    /// faces of the collection.
+    pub fn from_collection(
+        data: Arc<[u8]>,
+        index: u32,
+    ) -> Result<Self, owned_ttf_parser::FaceParsingError> {
+        let face = CollectionFace::parse(data, index)?;
+        Ok(Self::from_data(FaceData::Collection(Arc::new(face))))
+    }
+
+    fn from_data(face: FaceData) -> Self {
         Self {
-            face: Arc::new(face),
+            face,
+            mode: SdfMode::default(),
+            pixels_per_em: 100.0,
+            range: 6,
+            variation: FontVariation::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: SdfMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn set_mode(&mut self, mode: SdfMode) {
+        self.mode = mode;
+    }
+
+    pub fn mode(&self) -> SdfMode {
+        self.mode
+    }
+
+    /// Sets the resolution of generated glyphs in texels per em.
+    pub fn with_pixels_per_em(mut self, pixels_per_em: f64) -> Self {
+        self.pixels_per_em = pixels_per_em;
+        self
+    }
+
+    pub fn pixels_per_em(&self) -> f64 {
+        self.pixels_per_em
+    }
+
+    /// Sets the distance range in texels, which is also the padding around each glyph.
+    pub fn with_range(mut self, range: u8) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn range(&self) -> u8 {
+        self.range
+    }
+
+    /// Returns an instance of this font with the given axis coordinates applied on top of its
+    /// own variation. The face data is shared with `self`.
+    pub fn instance(&self, variation: &FontVariation) -> Font {
+        let mut font = self.clone();
+        font.variation = self.variation.merged(variation);
+        font
+    }
+
+    pub fn with_variation(mut self, variation: FontVariation) -> Self {
+        self.variation = variation;
+        self
+    }
+
+    pub fn variation(&self) -> &FontVariation {
+        &self.variation
+    }
+
+    /// Lists the variation axes of a variable font.
+    pub fn variation_axes(&self) -> Vec<VariationAxis> {
+        self.face().variation_axes().into_iter().collect()
+    }
+
+    pub(super) fn face(&self) -> owned_ttf_parser::Face<'_> {
+        let mut face = match &self.face {
+            FaceData::Owned(face) => face.as_face_ref().clone(),
+            FaceData::Collection(face) => face.face().clone(),
+        };
+        for (axis, value) in self.variation.coordinates.iter() {
+            face.set_variation(Tag::from_bytes(axis), *value);
         }
+        face
+    }
+
+    pub fn glyph_id(&self, code_point: char) -> Option<GlyphId> {
+        self.face().glyph_index(code_point)
     }
 
     pub fn glyph(&self, code_point: char) -> Option<GlyphInfo> {
-        let face = self.face.clone();
-        let face = face.as_ref().as_face_ref();
+        self.glyph_id(code_point).map(|id| self.glyph_info(id))
+    }
 
-        let id = face.glyph_index(code_point)?;
+    pub fn glyph_info(&self, id: GlyphId) -> GlyphInfo {
+        let face = self.face();
 
         let bounds = face.glyph_bounding_box(id).unwrap_or(Rect {
             x_min: 0,
@@ -55,7 +305,7 @@ impl Font {
         });
         let scale = 1f32 / face.units_per_em() as f32;
 
-        Some(GlyphInfo {
+        GlyphInfo {
             id,
             advance: Vec2::new(
                 face.glyph_hor_advance(id).unwrap_or_default() as f32,
@@ -66,7 +316,80 @@ impl Font {
                 (bounds.x_max - bounds.x_min) as f32,
                 (bounds.y_max - bounds.y_min) as f32,
             ) * scale,
-        })
+        }
+    }
+
+    /// Horizontal kerning adjustment between two adjacent glyphs in em units.
+    ///
+    /// Uses the pair adjustments of the GPOS `kern` feature, like shapers the legacy `kern`
+    /// table is only used for fonts without one.
+    pub fn kerning(&self, left: GlyphId, right: GlyphId) -> f32 {
+        let face = self.face();
+
+        let kerning = match Self::gpos_kern_feature(&face) {
+            Some((gpos, feature)) => Self::gpos_kerning(&gpos, &feature, left, right),
+            None => Self::kern_table_kerning(&face, left, right),
+        }
+        .unwrap_or_default();
+        kerning as f32 / face.units_per_em() as f32
+    }
+
+    /// The `kern` feature of the default language of the script used for text without a
+    /// script of its own.
+    fn gpos_kern_feature<'a>(
+        face: &owned_ttf_parser::Face<'a>,
+    ) -> Option<(LayoutTable<'a>, Feature<'a>)> {
+        let gpos = face.tables().gpos?;
+        // the scripts HarfBuzz falls back to, in order
+        let language = [b"DFLT", b"dflt", b"latn"]
+            .into_iter()
+            .find_map(|tag| gpos.scripts.find(Tag::from_bytes(tag)))?
+            .default_language?;
+        let feature = language
+            .feature_indices
+            .into_iter()
+            .filter_map(|index| gpos.features.get(index))
+            .find(|feature| feature.tag == Tag::from_bytes(b"kern"))?;
+        Some((gpos, feature))
+    }
+
+    fn gpos_kerning(
+        gpos: &LayoutTable,
+        feature: &Feature,
+        left: GlyphId,
+        right: GlyphId,
+    ) -> Option<i16> {
+        feature
+            .lookup_indices
+            .into_iter()
+            .filter_map(|index| gpos.lookups.get(index))
+            .flat_map(|lookup| lookup.subtables.into_iter::<PositioningSubtable>())
+            .find_map(|subtable| {
+                let PositioningSubtable::Pair(adjustment) = subtable else {
+                    return None;
+                };
+                let coverage_index = adjustment.coverage().get(left)?;
+                let (record, _) = match adjustment {
+                    PairAdjustment::Format1 { sets, .. } => sets.get(coverage_index)?.get(right)?,
+                    PairAdjustment::Format2 {
+                        classes, matrix, ..
+                    } => matrix.get((classes.0.get(left), classes.1.get(right)))?,
+                };
+                Some(record.x_advance)
+            })
+    }
+
+    fn kern_table_kerning(
+        face: &owned_ttf_parser::Face,
+        left: GlyphId,
+        right: GlyphId,
+    ) -> Option<i16> {
+        face.tables()
+            .kern?
+            .subtables
+            .into_iter()
+            .filter(|subtable| subtable.horizontal && !subtable.has_cross_stream)
+            .find_map(|subtable| subtable.glyphs_kerning(left, right))
     }
 
     fn load_from_face(
@@ -83,11 +406,9 @@ impl Font {
     }
 
     pub fn generate(&self, glyph_id: GlyphId, range: f64) -> Option<Image> {
-        let face = self.face.clone();
-        let face = face.as_ref().as_face_ref();
+        let face = self.face();
 
-        // TODO: don't hard code the scale..
-        let scale = (1.0f64 / face.units_per_em() as f64) * 100f64;
+        let scale = self.pixels_per_em / face.units_per_em() as f64;
 
         let bbox = face.glyph_bounding_box(glyph_id)?;
         let transformation = nalgebra::convert::<_, Affine2<f64>>(Similarity2::new(
@@ -98,32 +419,50 @@ impl Font {
             0.0,
             scale,
         ));
-        let mut shape = Self::load_from_face(face, glyph_id);
+        let mut shape = Self::load_from_face(&face, glyph_id);
         shape.transform(&transformation);
 
         let width = ((bbox.x_max as f64 - bbox.x_min as f64) * scale + range * 2f64).ceil() as u32;
         let height = ((bbox.y_max as f64 - bbox.y_min as f64) * scale + range * 2f64).ceil() as u32;
 
-        // let colored_shape = Shape::edge_coloring_simple(shape, 0.01f64, 69420);
-        // let prepared_colored_shape = colored_shape.prepare();
-        // let mut msdf = RgbImage::new(width, height);
-        // fdsm::generate::generate_msdf(&prepared_colored_shape, range, &mut msdf);
-        // fdsm::render::correct_sign_msdf(&mut msdf, &prepared_colored_shape, FillRule::Nonzero);
-
-        // let mut msdf_rgba = RgbaImage::new(width, height);
-        // for (output, chunk) in msdf_rgba.chunks_exact_mut(4).zip(msdf.chunks_exact(3)) {
-        //     output.copy_from_slice(&[chunk[0], chunk[1], chunk[2], 0]);
-        // }
-
-        let prepared_shape = shape.prepare();
-        let mut sdf = GrayImage::new(width, height);
-        fdsm::generate::generate_sdf(&prepared_shape, range, &mut sdf);
-        fdsm::render::correct_sign_sdf(&mut sdf, &prepared_shape, FillRule::Nonzero);
-
-        let mut msdf_rgba = RgbaImage::new(width, height);
-        for (output, luma) in msdf_rgba.chunks_exact_mut(4).zip(sdf.iter()) {
-            output.copy_from_slice(&[0, 0, 0, *luma]);
-        }
+        let data = match self.mode {
+            SdfMode::Sdf => {
+                let prepared_shape = shape.prepare();
+                let mut sdf = GrayImage::new(width, height);
+                fdsm::generate::generate_sdf(&prepared_shape, range, &mut sdf);
+                fdsm::render::correct_sign_sdf(&mut sdf, &prepared_shape, FillRule::Nonzero);
+                sdf.into_raw()
+            }
+            SdfMode::Msdf => {
+                let colored_shape = Shape::edge_coloring_simple(shape, 0.03, 69420);
+                let prepared_colored_shape = colored_shape.prepare();
+                let mut msdf = RgbImage::new(width, height);
+                fdsm::generate::generate_msdf(&prepared_colored_shape, range, &mut msdf);
+                fdsm::render::correct_sign_msdf(
+                    &mut msdf,
+                    &prepared_colored_shape,
+                    FillRule::Nonzero,
+                );
+
+                let mut msdf_rgba = RgbaImage::new(width, height);
+                for (output, chunk) in msdf_rgba.chunks_exact_mut(4).zip(msdf.chunks_exact(3)) {
+                    output.copy_from_slice(&[chunk[0], chunk[1], chunk[2], 0]);
+                }
+                msdf_rgba.into_raw()
+            }
+            SdfMode::Mtsdf => {
+                let colored_shape = Shape::edge_coloring_simple(shape, 0.03, 69420);
+                let prepared_colored_shape = colored_shape.prepare();
+                let mut mtsdf = RgbaImage::new(width, height);
+                fdsm::generate::generate_mtsdf(&prepared_colored_shape, range, &mut mtsdf);
+                fdsm::render::correct_sign_mtsdf(
+                    &mut mtsdf,
+                    &prepared_colored_shape,
+                    FillRule::Nonzero,
+                );
+                mtsdf.into_raw()
+            }
+        };
 
         Some(Image::new(
             Extent3d {
@@ -132,17 +471,52 @@ impl Font {
                 depth_or_array_layers: 1,
             },
             TextureDimension::D2,
-            msdf_rgba.into_raw(),
-            TextureFormat::Rgba8Unorm,
+            data,
+            self.mode.texture_format(),
             RenderAssetUsages::MAIN_WORLD,
         ))
     }
 
     pub fn line_gap(&self) -> f64 {
-        let face = self.face.clone();
-        let face = face.as_ref().as_face_ref();
+        let face = self.face();
         face.height() as f64 / face.units_per_em() as f64
     }
+
+    pub fn ascender(&self) -> f64 {
+        let face = self.face();
+        face.ascender() as f64 / face.units_per_em() as f64
+    }
+
+    pub fn descender(&self) -> f64 {
+        let face = self.face();
+        face.descender() as f64 / face.units_per_em() as f64
+    }
+
+    /// Position of the underline center relative to the baseline and its thickness in em units.
+    pub fn underline_metrics(&self) -> (f64, f64) {
+        let face = self.face();
+        let scale = 1.0 / face.units_per_em() as f64;
+        face.underline_metrics().map_or((-0.1, 0.05), |metrics| {
+            (
+                metrics.position as f64 * scale,
+                metrics.thickness as f64 * scale,
+            )
+        })
+    }
+
+    /// Position of the strikethrough center relative to the baseline and its thickness in em
+    /// units.
+    pub fn strikeout_metrics(&self) -> (f64, f64) {
+        let face = self.face();
+        let scale = 1.0 / face.units_per_em() as f64;
+        // the position is the top of the stroke
+        face.strikeout_metrics().map_or((0.25, 0.05), |metrics| {
+            (
+                (metrics.position as f64 - metrics.thickness as f64 / 2.0) * scale,
+                metrics.thickness as f64 * scale,
+            )
+        })
+    }
 }
 
 // stolen from fdsm ttf-importer
@@ -226,3 +600,149 @@ impl OutlineBuilder for ShapeBuilder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::test_font::{self, A, B, C, SPACE};
+
+    #[test]
+    fn kern_table_kerning() {
+        let font = test_font::font();
+        let face = font.face();
+        assert_eq!(Font::kern_table_kerning(&face, A, B), Some(-100));
+        assert_eq!(Font::kern_table_kerning(&face, B, C), Some(-30));
+        assert_eq!(Font::kern_table_kerning(&face, B, A), None);
+        assert_eq!(Font::kern_table_kerning(&face, A, SPACE), None);
+    }
+
+    #[test]
+    fn gpos_kerning() {
+        let font = test_font::font();
+        let face = font.face();
+        let (gpos, feature) = Font::gpos_kern_feature(&face).unwrap();
+        // the feature of the `DFLT` script, not the first `kern` feature
+        assert_eq!(feature.lookup_indices.len(), 1);
+        let kerning = |left, right| Font::gpos_kerning(&gpos, &feature, left, right);
+        assert_eq!(kerning(A, B), Some(-80));
+        assert_eq!(kerning(C, A), Some(-40));
+        // the first glyph is covered, the pair is not
+        assert_eq!(kerning(A, C), None);
+        assert_eq!(kerning(B, C), None);
+    }
+
+    #[test]
+    fn kerning_ignores_the_kern_table_with_gpos() {
+        let font = test_font::font();
+        assert_eq!(font.kerning(A, B), -80.0 / 1000.0);
+        assert_eq!(font.kerning(C, A), -40.0 / 1000.0);
+        assert_eq!(font.kerning(B, C), 0.0);
+        assert_eq!(font.kerning(B, A), 0.0);
+    }
+
+    #[test]
+    fn kerning_uses_the_kern_table_without_gpos() {
+        let font = test_font::fallback();
+        assert!(Font::gpos_kern_feature(&font.face()).is_none());
+        assert_eq!(font.kerning(A, B), -100.0 / 1000.0);
+        assert_eq!(font.kerning(B, C), -30.0 / 1000.0);
+        assert_eq!(font.kerning(C, A), 0.0);
+    }
+
+    /// The test font as the only face of a font collection.
+    fn collection() -> Vec<u8> {
+        let mut font = test_font::data();
+        // table offsets are relative to the start of the collection
+        let table_count = u16::from_be_bytes([font[4], font[5]]) as usize;
+        for table in 0..table_count {
+            let at = 12 + 16 * table + 8;
+            let offset = u32::from_be_bytes(font[at..at + 4].try_into().unwrap());
+            font[at..at + 4].copy_from_slice(&(offset + 16).to_be_bytes());
+        }
+        [
+            &b"ttcf"[..],
+            &[0, 1, 0, 0],
+            &1u32.to_be_bytes(),
+            &16u32.to_be_bytes(),
+            &font,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn collection_faces_share_the_data() {
+        let data: Arc<[u8]> = collection().into();
+        assert_eq!(owned_ttf_parser::fonts_in_collection(&data), Some(1));
+        assert!(Font::from_collection(data.clone(), 1).is_err());
+
+        let font = Font::from_collection(data.clone(), 0).unwrap();
+        drop(data);
+        let instance = font.instance(&FontVariation::weight(700.0));
+        drop(font);
+        assert_eq!(instance.glyph_id('a'), Some(A));
+        assert_eq!(instance.kerning(A, B), -80.0 / 1000.0);
+        assert_eq!(instance.ascender(), 0.8);
+    }
+
+    fn hash(variation: &FontVariation) -> u64 {
+        let mut hasher = std::hash::DefaultHasher::new();
+        variation.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn variation_with_sorts_and_replaces_axes() {
+        let variation = FontVariation::new()
+            .with(b"wght", 700.0)
+            .with(b"opsz", 12.0)
+            .with(b"wght", 400.0);
+        assert_eq!(
+            variation.coordinates,
+            vec![(*b"opsz", 12.0), (*b"wght", 400.0)]
+        );
+        assert!(!variation.is_default());
+        assert!(FontVariation::new().is_default());
+    }
+
+    #[test]
+    fn merged_variation_prefers_other() {
+        let base = FontVariation::new()
+            .with(b"wght", 700.0)
+            .with(b"wdth", 75.0);
+        let merged = base.merged(&FontVariation::weight(300.0).with(b"opsz", 12.0));
+        assert_eq!(
+            merged,
+            FontVariation::new()
+                .with(b"opsz", 12.0)
+                .with(b"wdth", 75.0)
+                .with(b"wght", 300.0)
+        );
+    }
+
+    #[test]
+    fn variation_equality_ignores_insertion_order() {
+        let a = FontVariation::new()
+            .with(b"wght", 700.0)
+            .with(b"wdth", 75.0);
+        let b = FontVariation::new()
+            .with(b"wdth", 75.0)
+            .with(b"wght", 700.0);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(a, FontVariation::weight(700.0));
+        assert_ne!(a, b.clone().with(b"wght", 701.0));
+    }
+
+    #[test]
+    fn variation_serializes_tags_as_strings() {
+        let variation = FontVariation::new()
+            .with(b"wght", 700.0)
+            .with(b"wdth", 75.0);
+        let json = serde_json::to_string(&variation).unwrap();
+        assert_eq!(json, r#"{"wdth":75.0,"wght":700.0}"#);
+        let parsed: FontVariation = serde_json::from_str(r#"{"wght":700.0,"wdth":75.0}"#).unwrap();
+        assert_eq!(parsed, variation);
+        assert_eq!(hash(&parsed), hash(&variation));
+        assert!(serde_json::from_str::<FontVariation>(r#"{"weight":700.0}"#).is_err());
+    }
+}
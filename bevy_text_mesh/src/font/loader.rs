@@ -2,10 +2,12 @@ use bevy::{
     asset::{io::Reader, AssetLoader, LoadContext},
     prelude::*,
 };
-use std::{future::Future, pin::Pin};
+use owned_ttf_parser::name_id;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, pin::Pin, sync::Arc};
 use thiserror::Error;
 
-use super::font::Font;
+use super::font::{Font, FontVariation, SdfMode};
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -16,6 +18,81 @@ pub enum FontLoaderError {
     /// An [InvalidFont](ab_glyph::InvalidFont) Error
     #[error(transparent)]
     FontInvalid(#[from] owned_ttf_parser::FaceParsingError),
+    /// No face in the font collection matches the [`FaceSelector`]
+    #[error("no face matching {0:?} found in font collection")]
+    FaceNotFound(FaceSelector),
+}
+
+/// Selects a face from a font collection (`.ttc`/`.otc`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum FaceSelector {
+    /// The face at the given index.
+    Index(u32),
+    /// The first face with a matching family and, if given, style (subfamily) name.
+    Name {
+        family: String,
+        style: Option<String>,
+    },
+}
+
+impl Default for FaceSelector {
+    fn default() -> Self {
+        Self::Index(0)
+    }
+}
+
+impl FaceSelector {
+    fn find(&self, data: &[u8], face_count: u32) -> Option<u32> {
+        let (family, style) = match self {
+            Self::Index(index) => return (*index < face_count).then_some(*index),
+            Self::Name { family, style } => (family, style),
+        };
+        (0..face_count).find(|index| {
+            let Ok(face) = owned_ttf_parser::Face::parse(data, *index) else {
+                return false;
+            };
+            let has_name = |ids: [u16; 2], expected: &str| {
+                face.names().into_iter().any(|name| {
+                    ids.contains(&name.name_id)
+                        && name
+                            .to_string()
+                            .is_some_and(|name| name.eq_ignore_ascii_case(expected))
+                })
+            };
+            has_name([name_id::FAMILY, name_id::TYPOGRAPHIC_FAMILY], family)
+                && style.as_ref().is_none_or(|style| {
+                    has_name([name_id::SUBFAMILY, name_id::TYPOGRAPHIC_SUBFAMILY], style)
+                })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FontLoaderSettings {
+    /// Resolution of the generated distance fields in texels per em.
+    pub pixels_per_em: f64,
+    /// Distance range in texels, larger ranges allow wider outlines and effects.
+    pub range: u8,
+    pub mode: SdfMode,
+    /// The face used for the main asset when loading a font collection.
+    ///
+    /// Every face of a collection is also available as a labeled asset by its index, e.g.
+    /// `fonts/Noto.ttc#2`.
+    pub face: FaceSelector,
+    /// Variable font axis coordinates of the loaded font.
+    pub variation: FontVariation,
+}
+
+impl Default for FontLoaderSettings {
+    fn default() -> Self {
+        Self {
+            pixels_per_em: 100.0,
+            range: 6,
+            mode: SdfMode::default(),
+            face: FaceSelector::default(),
+            variation: FontVariation::default(),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -23,23 +100,45 @@ pub struct FontLoader;
 
 impl AssetLoader for FontLoader {
     type Asset = Font;
-    type Settings = ();
+    type Settings = FontLoaderSettings;
     type Error = FontLoaderError;
     fn load<'a>(
         &'a self,
         reader: &'a mut Reader,
-        _settings: &'a (),
-        _load_context: &'a mut LoadContext,
+        settings: &'a FontLoaderSettings,
+        load_context: &'a mut LoadContext,
     ) -> Pin<Box<dyn Future<Output = Result<Font, Self::Error>> + Send + 'a>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
             bevy::asset::AsyncReadExt::read_to_end(reader, &mut bytes).await?;
-            let face = owned_ttf_parser::OwnedFace::from_vec(bytes, 0)?;
-            Ok(Font::from(face))
+            let configure = |font: Font| {
+                font.with_mode(settings.mode)
+                    .with_pixels_per_em(settings.pixels_per_em)
+                    .with_range(settings.range)
+                    .with_variation(settings.variation.clone())
+            };
+
+            let Some(face_count) = owned_ttf_parser::fonts_in_collection(&bytes) else {
+                let face = owned_ttf_parser::OwnedFace::from_vec(bytes, 0)?;
+                return Ok(configure(Font::from(face)));
+            };
+            let index = settings
+                .face
+                .find(&bytes, face_count)
+                .ok_or_else(|| FontLoaderError::FaceNotFound(settings.face.clone()))?;
+            // all faces share the data of the collection
+            let data: Arc<[u8]> = bytes.into();
+            for label in (0..face_count).filter(|label| *label != index) {
+                let face = Font::from_collection(data.clone(), label)?;
+                load_context.add_labeled_asset(label.to_string(), configure(face));
+            }
+            let font = configure(Font::from_collection(data, index)?);
+            load_context.add_labeled_asset(index.to_string(), font.clone());
+            Ok(font)
         })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["ttf", "otf"]
+        &["ttf", "otf", "ttc", "otc"]
     }
 }
@@ -1,23 +1,44 @@
+use baked::BakedAtlasLoader;
 use bevy::{
-    app::{App, Plugin},
+    app::{App, Plugin, Update},
     asset::AssetApp,
 };
 use loader::FontLoader;
 
 mod atlas;
+mod baked;
+mod charset;
+mod family;
 mod font;
 mod loader;
+mod shaping;
+#[cfg(test)]
+pub(crate) mod test_font;
 
-pub use atlas::FontAtlases;
 pub use atlas::FontData;
+pub use atlas::{AtlasEviction, AtlasGrowth, FontAtlases};
+pub use baked::{
+    BakedAtlasError, BakedAtlasFile, BakedAtlasLoaderSettings, BakedFontAtlas, BakedGlyph,
+};
+pub use charset::CharacterSet;
+pub use family::FontFamily;
 pub use font::Font;
+pub use font::{FontVariation, GlyphId, GlyphInfo, SdfMode};
+pub use loader::{FaceSelector, FontLoaderSettings};
+pub use shaping::ShapedGlyph;
 
 pub struct SdfFontPlugin;
 
 impl Plugin for SdfFontPlugin {
     fn build(&self, app: &mut App) {
         app.init_asset::<Font>()
+            .init_asset::<BakedFontAtlas>()
             .init_asset_loader::<FontLoader>()
-            .init_resource::<FontAtlases>();
+            .init_asset_loader::<BakedAtlasLoader>()
+            .init_resource::<FontAtlases>()
+            .add_systems(
+                Update,
+                (baked::add_baked_atlases, atlas::remove_stale_fonts),
+            );
     }
 }
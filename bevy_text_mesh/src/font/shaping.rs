@@ -0,0 +1,68 @@
+use bevy::math::Vec2;
+
+use super::font::{Font, GlyphId};
+
+/// A glyph produced by shaping a run of text, with metrics in em units.
+#[derive(Debug, Clone)]
+pub struct ShapedGlyph {
+    pub id: GlyphId,
+    /// Byte offset of the first character in the shaped text that maps to this glyph.
+    pub cluster: usize,
+    /// Horizontal pen advance after this glyph.
+    pub advance: f32,
+    /// Offset of the glyph from the pen position, without affecting the advance.
+    pub offset: Vec2,
+}
+
+impl Font {
+    /// Shapes a single line of text into positioned glyphs in visual order.
+    ///
+    /// Code points missing from the font map to the `.notdef` glyph (id 0).
+    #[cfg(feature = "shaping")]
+    pub fn shape(&self, text: &str) -> Vec<ShapedGlyph> {
+        let face = rustybuzz::Face::from_face(self.face());
+        let scale = 1f32 / face.units_per_em() as f32;
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        let output = rustybuzz::shape(&face, &[], buffer);
+
+        output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                id: GlyphId(info.glyph_id as u16),
+                cluster: info.cluster as usize,
+                advance: position.x_advance as f32 * scale,
+                offset: Vec2::new(position.x_offset as f32, position.y_offset as f32) * scale,
+            })
+            .collect()
+    }
+
+    /// Shapes a single line of text by mapping each code point to one glyph, applying pair
+    /// kerning between neighbours.
+    ///
+    /// Code points missing from the font map to the `.notdef` glyph (id 0).
+    #[cfg(not(feature = "shaping"))]
+    pub fn shape(&self, text: &str) -> Vec<ShapedGlyph> {
+        let face = self.face();
+        let scale = 1f32 / face.units_per_em() as f32;
+
+        let mut glyphs: Vec<ShapedGlyph> = Vec::with_capacity(text.len());
+        for (cluster, character) in text.char_indices() {
+            let id = face.glyph_index(character).unwrap_or(GlyphId(0));
+            if let Some(previous) = glyphs.last_mut() {
+                previous.advance += self.kerning(previous.id, id);
+            }
+            glyphs.push(ShapedGlyph {
+                id,
+                cluster,
+                advance: face.glyph_hor_advance(id).unwrap_or_default() as f32 * scale,
+                offset: Vec2::ZERO,
+            });
+        }
+        glyphs
+    }
+}
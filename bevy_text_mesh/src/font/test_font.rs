@@ -0,0 +1,196 @@
+//! A minimal font built in memory for unit tests.
+//!
+//! It has 1000 units per em, an ascender of 800 and a descender of -200 without line gap, so a
+//! line is 1 em high. Every glyph advances by half an em and its outline is a box:
+//!
+//! - `.notdef` and `b` span 50..450 × 0..700
+//! - space has no outline
+//! - `a` spans 50..450 × 0..500
+//! - `c` spans 50..450 × -200..500
+//!
+//! The `kern` table adjusts `a b` by -100 and `b c` by -30 units. The GPOS `kern` feature of
+//! the `DFLT` script adjusts `a b` by -80 and `c a` by -40 units, an empty `kern` feature
+//! precedes it.
+//!
+//! The [`fallback`] font has the same glyphs and `kern` table without GPOS, but maps only
+//! space and `c`, and its line is taller with an ascender of 900 and a descender of -300.
+
+use owned_ttf_parser::OwnedFace;
+use std::ops::RangeInclusive;
+
+use super::font::{Font, GlyphId};
+
+pub const SPACE: GlyphId = GlyphId(1);
+pub const A: GlyphId = GlyphId(2);
+pub const B: GlyphId = GlyphId(3);
+pub const C: GlyphId = GlyphId(4);
+
+/// Advance and outline box (x min, y min, x max, y max) of each glyph.
+const GLYPHS: [(u16, Option<[i16; 4]>); 5] = [
+    (500, Some([50, 0, 450, 700])),
+    (500, None),
+    (500, Some([50, 0, 450, 500])),
+    (500, Some([50, 0, 450, 700])),
+    (500, Some([50, -200, 450, 500])),
+];
+
+pub fn font() -> Font {
+    Font::from(OwnedFace::from_vec(data(), 0).unwrap())
+}
+
+pub fn fallback() -> Font {
+    Font::from(OwnedFace::from_vec(build(900, -300, 'c'..='c', false), 0).unwrap())
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) -> &mut Self {
+        self.0.push(value);
+        self
+    }
+
+    fn u16(&mut self, value: u16) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn i16(&mut self, value: i16) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.0.extend_from_slice(value);
+        self
+    }
+}
+
+pub fn data() -> Vec<u8> {
+    build(800, -200, 'a'..='c', true)
+}
+
+/// Builds the font with the given line metrics, mapping `letters` from `a` to `c` to their
+/// glyphs, and optionally a GPOS table.
+fn build(ascender: i16, descender: i16, letters: RangeInclusive<char>, with_gpos: bool) -> Vec<u8> {
+    let glyph_count = GLYPHS.len() as u16;
+
+    let mut head = Writer::default();
+    head.u32(0x00010000).u32(0x00010000).u32(0).u32(0x5F0F3CF5);
+    // flags, units per em, created and modified dates
+    head.u16(0).u16(1000).u32(0).u32(0).u32(0).u32(0);
+    head.i16(0).i16(-200).i16(500).i16(800);
+    // mac style, lowest readable size, direction hint, long loca offsets, glyph data format
+    head.u16(0).u16(8).i16(2).i16(1).i16(0);
+
+    let mut hhea = Writer::default();
+    hhea.u32(0x00010000).i16(ascender).i16(descender).i16(0);
+    // max advance, min side bearings, max extent, caret slope and offset
+    hhea.u16(500).i16(0).i16(0).i16(450).i16(1).i16(0).i16(0);
+    hhea.i16(0).i16(0).i16(0).i16(0).i16(0).u16(glyph_count);
+
+    let mut maxp = Writer::default();
+    maxp.u32(0x00005000).u16(glyph_count);
+
+    let mut hmtx = Writer::default();
+    for (advance, bounds) in GLYPHS {
+        hmtx.u16(advance).i16(bounds.map_or(0, |bounds| bounds[0]));
+    }
+
+    let mut cmap = Writer::default();
+    // a single format 12 subtable for Unicode
+    cmap.u16(0).u16(1).u16(0).u16(4).u32(12);
+    cmap.u16(12).u16(0).u32(16 + 12 * 2).u32(0).u32(2);
+    cmap.u32(' ' as u32).u32(' ' as u32).u32(SPACE.0 as u32);
+    let (first, last) = (*letters.start() as u32, *letters.end() as u32);
+    cmap.u32(first)
+        .u32(last)
+        .u32(A.0 as u32 + first - 'a' as u32);
+
+    let mut glyf = Writer::default();
+    let mut loca = Writer::default();
+    for (_, bounds) in GLYPHS {
+        loca.u32(glyf.0.len() as u32);
+        let Some([x_min, y_min, x_max, y_max]) = bounds else {
+            continue;
+        };
+        // one clockwise contour of four on-curve points with word deltas
+        glyf.i16(1).i16(x_min).i16(y_min).i16(x_max).i16(y_max);
+        glyf.u16(3).u16(0).u8(1).u8(1).u8(1).u8(1);
+        glyf.i16(x_min).i16(0).i16(x_max - x_min).i16(0);
+        glyf.i16(y_min).i16(y_max - y_min).i16(0).i16(y_min - y_max);
+    }
+    loca.u32(glyf.0.len() as u32);
+
+    let kern_pairs = [(A, B, -100), (B, C, -30)];
+    let mut kern = Writer::default();
+    kern.u16(0).u16(1);
+    // horizontal format 0 subtable
+    let length = 14 + 6 * kern_pairs.len() as u16;
+    kern.u16(0).u16(length).u16(0x0001);
+    kern.u16(kern_pairs.len() as u16).u16(12).u16(1).u16(0);
+    for (left, right, value) in kern_pairs {
+        kern.u16(left.0).u16(right.0).i16(value);
+    }
+
+    let mut gpos = Writer::default();
+    // header with the script, feature and lookup list offsets
+    gpos.u16(1).u16(0).u16(10).u16(30).u16(54);
+    // the `DFLT` script, whose default language uses the second feature
+    gpos.u16(1).bytes(b"DFLT").u16(8);
+    gpos.u16(4).u16(0);
+    gpos.u16(0).u16(0xFFFF).u16(1).u16(1);
+    // two `kern` features, the first one without lookups and the second one using lookup 0
+    gpos.u16(2).bytes(b"kern").u16(14).bytes(b"kern").u16(18);
+    gpos.u16(0).u16(0);
+    gpos.u16(0).u16(1).u16(0);
+    // one pair adjustment lookup
+    gpos.u16(1).u16(4);
+    gpos.u16(2).u16(0).u16(1).u16(8);
+    // format 1 with x advances of the first glyph, coverage and pair sets follow
+    gpos.u16(1).u16(14).u16(0x0004).u16(0);
+    gpos.u16(2).u16(22).u16(28);
+    gpos.u16(1).u16(2).u16(A.0).u16(C.0);
+    gpos.u16(1).u16(B.0).i16(-80);
+    gpos.u16(1).u16(A.0).i16(-40);
+
+    // sorted by tag
+    let tables: Vec<_> = [
+        (b"GPOS", gpos),
+        (b"cmap", cmap),
+        (b"glyf", glyf),
+        (b"head", head),
+        (b"hhea", hhea),
+        (b"hmtx", hmtx),
+        (b"kern", kern),
+        (b"loca", loca),
+        (b"maxp", maxp),
+    ]
+    .into_iter()
+    .filter(|(tag, _)| with_gpos || *tag != b"GPOS")
+    .collect();
+    let mut font = Writer::default();
+    let table_count = tables.len() as u16;
+    let entry_selector = table_count.ilog2() as u16;
+    font.u32(0x00010000).u16(table_count);
+    font.u16(16 << entry_selector)
+        .u16(entry_selector)
+        .u16(16 * table_count - (16 << entry_selector));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        let length = table.0.len() as u32;
+        font.bytes(*tag).u32(0).u32(offset as u32).u32(length);
+        offset += table.0.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        font.bytes(&table.0);
+        font.bytes(&[0; 3][..table.0.len().next_multiple_of(4) - table.0.len()]);
+    }
+    font.0
+}
@@ -3,10 +3,24 @@ mod plugin;
 mod text_mesh;
 
 pub use plugin::Text3dPlugin;
+pub use text_mesh::DecorationLine;
 pub use text_mesh::Glyph;
+pub use text_mesh::Text3d;
+pub use text_mesh::Text3dStyle;
 pub use text_mesh::TextMesh;
+pub use text_mesh::TextMeshComplete;
+pub use text_mesh::{layout_spans, layout_text, LayoutSpan, TextAlignment, TextAnchor, TextLayout};
+pub use text_mesh::{Text3dSpan, TextDecoration, TextSpanStyle};
+pub use text_mesh::{TextGlow, TextOutline, TextShadow};
 
 pub use font::Font;
 pub use font::FontData;
+pub use font::FontFamily;
+pub use font::{
+    BakedAtlasError, BakedAtlasFile, BakedAtlasLoaderSettings, BakedFontAtlas, BakedGlyph,
+};
+pub use font::{FaceSelector, FontLoaderSettings};
+pub use font::{FontVariation, GlyphId, GlyphInfo, SdfMode, ShapedGlyph};
 
-pub use font::FontAtlases;
+pub use font::CharacterSet;
+pub use font::{AtlasEviction, AtlasGrowth, FontAtlases};
@@ -0,0 +1,979 @@
+use bevy::asset::{AssetEvent, Assets, Handle};
+use bevy::color::{Color, ColorToComponents};
+use bevy::math::{Rect, Vec2};
+use bevy::prelude::{Component, DetectChanges, EventReader, Query, Ref, Res};
+use bevy::utils::{HashMap, HashSet};
+use std::ops::Range;
+
+use super::text_mesh::{DecorationLine, Glyph, TextGlow, TextOutline, TextShadow};
+use super::{Font, FontVariation, GlyphId, ShapedGlyph, TextMesh};
+
+/// Horizontal alignment of each line within the text block.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches wrapped lines to the block width, the last line of a paragraph is left aligned.
+    Justified,
+}
+
+/// The point of the text block that is placed at the entity origin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextAnchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    CenterLeft,
+    Center,
+    CenterRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+    /// Left end of the first line's baseline.
+    #[default]
+    BaselineLeft,
+    BaselineCenter,
+    BaselineRight,
+}
+
+impl TextAnchor {
+    /// Offset of the anchor within `bounds`.
+    fn offset(self, bounds: Rect) -> Vec2 {
+        let x = match self {
+            Self::TopLeft | Self::CenterLeft | Self::BottomLeft | Self::BaselineLeft => {
+                bounds.min.x
+            }
+            Self::TopCenter | Self::Center | Self::BottomCenter | Self::BaselineCenter => {
+                bounds.center().x
+            }
+            Self::TopRight | Self::CenterRight | Self::BottomRight | Self::BaselineRight => {
+                bounds.max.x
+            }
+        };
+        let y = match self {
+            Self::TopLeft | Self::TopCenter | Self::TopRight => bounds.max.y,
+            Self::CenterLeft | Self::Center | Self::CenterRight => bounds.center().y,
+            Self::BottomLeft | Self::BottomCenter | Self::BottomRight => bounds.min.y,
+            Self::BaselineLeft | Self::BaselineCenter | Self::BaselineRight => 0.0,
+        };
+        Vec2::new(x, y)
+    }
+}
+
+/// Lines drawn along the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+/// Style of a [`Text3d`], its variation, weight and effects replace those set on the
+/// [`TextMesh`].
+#[derive(Debug, Clone)]
+pub struct Text3dStyle {
+    /// Size of one em in world units.
+    pub font_size: f32,
+    pub color: Color,
+    /// Maximum width of a line in world units, longer lines are wrapped at word boundaries.
+    pub max_width: Option<f32>,
+    /// Multiplier applied to the font's line gap.
+    pub line_spacing: f32,
+    pub alignment: TextAlignment,
+    pub anchor: TextAnchor,
+    /// Variable font axis coordinates, applied on top of the font's own variation.
+    pub variation: FontVariation,
+    /// Weight offset of the glyph contour, see [`TextMesh::set_weight`].
+    pub weight: f32,
+    /// Slant of a synthetic italic as the tangent of its angle, e.g. `0.2` for about 11°.
+    pub skew: f32,
+    pub decoration: TextDecoration,
+    pub outline: TextOutline,
+    pub shadow: Option<TextShadow>,
+    pub glow: Option<TextGlow>,
+}
+
+impl Default for Text3dStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 1.0,
+            color: Color::WHITE,
+            max_width: None,
+            line_spacing: 1.0,
+            alignment: TextAlignment::default(),
+            anchor: TextAnchor::default(),
+            variation: FontVariation::default(),
+            weight: 0.0,
+            skew: 0.0,
+            decoration: TextDecoration::default(),
+            outline: TextOutline::default(),
+            shadow: None,
+            glow: None,
+        }
+    }
+}
+
+/// Style of a [`Text3dSpan`], unset fields are taken from the [`Text3dStyle`] of the text.
+#[derive(Debug, Clone, Default)]
+pub struct TextSpanStyle {
+    /// Font of the span, code points it is missing are taken from the fallbacks of the
+    /// [`TextMesh`] family.
+    pub font: Option<Handle<Font>>,
+    pub font_size: Option<f32>,
+    pub color: Option<Color>,
+    pub weight: Option<f32>,
+    pub skew: Option<f32>,
+    pub decoration: Option<TextDecoration>,
+}
+
+/// A section of text with its own style, following the text of [`Text3d`] in the same flow.
+#[derive(Debug, Clone, Default)]
+pub struct Text3dSpan {
+    pub text: String,
+    pub style: TextSpanStyle,
+}
+
+impl Text3dSpan {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            style: Default::default(),
+        }
+    }
+
+    pub fn with_style(mut self, style: TextSpanStyle) -> Self {
+        self.style = style;
+        self
+    }
+}
+
+/// Text that is laid out into the [`TextMesh`] on the same entity.
+///
+/// The `text` is the first span, styled by `style` alone, followed by the `spans`.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Text3d {
+    pub text: String,
+    pub style: Text3dStyle,
+    pub spans: Vec<Text3dSpan>,
+}
+
+impl Text3d {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            style: Default::default(),
+            spans: Vec::new(),
+        }
+    }
+
+    pub fn with_style(mut self, style: Text3dStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_span(mut self, span: Text3dSpan) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    /// Fonts of the spans that are not in `family`, each listed once.
+    fn span_fonts(&self, family: &[Handle<Font>]) -> Vec<Handle<Font>> {
+        let mut fonts: Vec<Handle<Font>> = Vec::new();
+        for font in self
+            .spans
+            .iter()
+            .filter_map(|span| span.style.font.as_ref())
+        {
+            if !family.contains(font) && !fonts.contains(font) {
+                fonts.push(font.clone());
+            }
+        }
+        fonts
+    }
+
+    /// Resolves all spans against the text style, `fonts` being those of the [`TextMesh`]
+    /// whose first `family_len` form its family.
+    fn layout_spans(&self, fonts: &[Handle<Font>], family_len: usize) -> Vec<LayoutSpan<'_>> {
+        let root = TextSpanStyle::default();
+        std::iter::once((self.text.as_str(), &root))
+            .chain(
+                self.spans
+                    .iter()
+                    .map(|span| (span.text.as_str(), &span.style)),
+            )
+            .map(|(text, style)| {
+                let primary = style
+                    .font
+                    .as_ref()
+                    .and_then(|font| fonts.iter().position(|other| other == font))
+                    .unwrap_or(0);
+                LayoutSpan {
+                    text,
+                    fonts: std::iter::once(primary)
+                        .chain((0..family_len).filter(|index| *index != primary))
+                        .collect(),
+                    font_size: style.font_size.unwrap_or(self.style.font_size),
+                    color: style.color.unwrap_or(self.style.color),
+                    weight: style
+                        .weight
+                        .map_or(0.0, |weight| weight - self.style.weight),
+                    skew: style.skew.unwrap_or(self.style.skew),
+                    decoration: style.decoration.unwrap_or(self.style.decoration),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A span of text with its resolved style, as input to [`layout_spans`].
+#[derive(Debug, Clone)]
+pub struct LayoutSpan<'a> {
+    pub text: &'a str,
+    /// Indices into the fonts passed to [`layout_spans`], the span's font followed by its
+    /// fallbacks. The first one provides the line metrics.
+    pub fonts: Vec<usize>,
+    pub font_size: f32,
+    pub color: Color,
+    /// Weight offset on top of the one of the [`TextMesh`], see [`Glyph::weight`].
+    pub weight: f32,
+    pub skew: f32,
+    pub decoration: TextDecoration,
+}
+
+pub struct TextLayout {
+    pub glyphs: Vec<Glyph>,
+    pub decorations: Vec<DecorationLine>,
+    /// All glyphs used by the text with the index of their font, each listed once.
+    pub glyph_ids: Vec<(usize, GlyphId)>,
+    pub line_count: usize,
+    /// Extent of the text block relative to the entity origin, from the descender of the last
+    /// line to the ascender of the first line.
+    pub bounds: Rect,
+}
+
+/// Vertical metrics of a span in world units.
+#[derive(Debug, Clone, Copy, Default)]
+struct LineMetrics {
+    ascender: f32,
+    descender: f32,
+    /// Distance from the baseline of the previous line, including the line spacing.
+    height: f32,
+}
+
+impl LineMetrics {
+    fn include(&mut self, other: LineMetrics) {
+        self.ascender = self.ascender.max(other.ascender);
+        self.descender = self.descender.min(other.descender);
+        self.height = self.height.max(other.height);
+    }
+}
+
+struct Line {
+    glyphs: Range<usize>,
+    /// Left edge of the sheared glyphs leaning past the line start, at most 0.
+    start: f32,
+    /// Advance up to the last non-whitespace character.
+    width: f32,
+    /// Number of word gaps, used to distribute the remaining space of justified lines.
+    gaps: usize,
+    /// Whether the line was broken by wrapping instead of a newline or the end of the text.
+    wrapped: bool,
+    /// Metrics of all spans on the line.
+    metrics: LineMetrics,
+}
+
+impl Line {
+    fn new(start: usize) -> Self {
+        Self {
+            glyphs: start..start,
+            start: 0.0,
+            width: 0.0,
+            gaps: 0,
+            wrapped: false,
+            metrics: LineMetrics::default(),
+        }
+    }
+
+    /// Ends this line at glyph `end`, returning it and starting the next line in its place.
+    fn finish(&mut self, end: usize, wrapped: bool) -> Line {
+        self.glyphs.end = end;
+        self.wrapped = wrapped;
+        std::mem::replace(self, Line::new(end))
+    }
+}
+
+/// A shaped glyph within a paragraph.
+struct Item {
+    span: usize,
+    font: usize,
+    glyph: ShapedGlyph,
+    whitespace: bool,
+}
+
+/// The part of a decoration line below a single glyph, before alignment.
+struct Segment {
+    line: usize,
+    /// Index of the glyph within all shaped glyphs, adjacent glyphs are merged into one line.
+    item: usize,
+    gap: usize,
+    x: Range<f32>,
+    /// Center and thickness relative to the baseline.
+    y: f32,
+    thickness: f32,
+    font: usize,
+    color: [f32; 4],
+    texel_size: f32,
+    whitespace: bool,
+}
+
+impl Segment {
+    fn continues(&self, other: &Segment) -> bool {
+        self.line == other.line
+            && self.item + 1 == other.item
+            && self.y == other.y
+            && self.thickness == other.thickness
+            && self.font == other.font
+            && self.color == other.color
+    }
+}
+
+/// Lays out `text` into glyphs positioned relative to [`Text3dStyle::anchor`].
+///
+/// Lines are broken at `'\n'` and, if [`Text3dStyle::max_width`] is set, before words that
+/// would exceed it. Subsequent lines are placed below the previous one.
+///
+/// Each paragraph is shaped with [`Font::shape`] before being broken into lines. Code points
+/// missing from the first (primary) font are taken from the following fallback fonts, the
+/// line metrics always come from the primary font.
+///
+/// # Panics
+///
+/// Panics if `fonts` is empty.
+pub fn layout_text(fonts: &[Font], text: &str, style: &Text3dStyle) -> TextLayout {
+    let span = LayoutSpan {
+        text,
+        fonts: (0..fonts.len()).collect(),
+        font_size: style.font_size,
+        color: style.color,
+        weight: 0.0,
+        skew: style.skew,
+        decoration: style.decoration,
+    };
+    layout_spans(fonts, &[span], style)
+}
+
+/// Lays out consecutive `spans` as one text, see [`layout_text`].
+///
+/// Words and paragraphs may continue across spans. Each line is as high as its largest span,
+/// and the style's font size and color are replaced by those of the spans.
+pub fn layout_spans(fonts: &[Font], spans: &[LayoutSpan], style: &Text3dStyle) -> TextLayout {
+    let max_width = style.max_width.unwrap_or(f32::INFINITY);
+    let span_metrics: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let font = &fonts[span.fonts[0]];
+            LineMetrics {
+                ascender: font.ascender() as f32 * span.font_size,
+                descender: font.descender() as f32 * span.font_size,
+                height: font.line_gap() as f32 * span.font_size * style.line_spacing,
+            }
+        })
+        .collect();
+    // center and thickness of the underline and strikethrough of each span
+    let span_decorations: Vec<Vec<(f32, f32)>> = spans
+        .iter()
+        .map(|span| {
+            let font = &fonts[span.fonts[0]];
+            [
+                (span.decoration.underline, font.underline_metrics()),
+                (span.decoration.strikethrough, font.strikeout_metrics()),
+            ]
+            .into_iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, (y, thickness))| {
+                (y as f32 * span.font_size, thickness as f32 * span.font_size)
+            })
+            .collect()
+        })
+        .collect();
+
+    // shape all paragraphs, each with the span it starts in
+    let mut paragraphs = vec![(0, Vec::new())];
+    for (span_index, span) in spans.iter().enumerate() {
+        for (index, text) in span.text.split('\n').enumerate() {
+            if index > 0 {
+                paragraphs.push((span_index, Vec::new()));
+            }
+            let text = text.trim_end_matches('\r');
+            let (_, items) = paragraphs.last_mut().unwrap();
+            items.extend(
+                shape_with_fallback(fonts, &span.fonts, text)
+                    .into_iter()
+                    .map(|(font, glyph)| Item {
+                        span: span_index,
+                        font,
+                        whitespace: text[glyph.cluster..]
+                            .chars()
+                            .next()
+                            .is_some_and(char::is_whitespace),
+                        glyph,
+                    }),
+            );
+        }
+    }
+
+    let mut infos = HashMap::new();
+    let mut glyphs = Vec::new();
+    // index of the word gap preceding each glyph within its line
+    let mut glyph_gaps = Vec::new();
+    let mut segments = Vec::new();
+    let mut item_index = 0;
+    let mut lines = Vec::new();
+    let mut line = Line::new(0);
+    let mut pen_x = 0.0;
+
+    for (index, (start_span, items)) in paragraphs.iter().enumerate() {
+        if index > 0 {
+            lines.push(line.finish(glyphs.len(), false));
+            pen_x = 0.0;
+        }
+        // an empty paragraph still takes the height of its span
+        if let Some(metrics) = span_metrics.get(*start_span) {
+            line.metrics.include(*metrics);
+        }
+
+        // each word keeps its trailing whitespace, which may hang over the line end
+        for word in items.chunk_by(|a, b| !a.whitespace || b.whitespace) {
+            let trailing = word.iter().rev().take_while(|item| item.whitespace).count();
+            let word_width: f32 = word[..word.len() - trailing]
+                .iter()
+                .map(|item| item.glyph.advance * spans[item.span].font_size)
+                .sum();
+            if pen_x > 0.0 && pen_x + word_width > max_width {
+                lines.push(line.finish(glyphs.len(), true));
+                pen_x = 0.0;
+            } else if pen_x > 0.0 {
+                line.gaps += 1;
+            }
+
+            for item in word {
+                let span = &spans[item.span];
+                line.metrics.include(span_metrics[item.span]);
+                let info = infos
+                    .entry((item.font, item.glyph.id))
+                    .or_insert_with(|| fonts[item.font].glyph_info(item.glyph.id));
+                let advance = item.glyph.advance * span.font_size;
+                // right edge of the sheared glyph, which may lean past the advance
+                let mut extent = 0.0;
+                // whitespace and other glyphs without an outline only advance the pen
+                if info.size.x > 0.0 && info.size.y > 0.0 {
+                    let size = info.size * span.font_size;
+                    let mut min =
+                        Vec2::X * pen_x + (item.glyph.offset + info.offset) * span.font_size;
+                    // the glyph is sheared around its bottom, move it to pivot on the baseline
+                    min.x += span.skew * min.y;
+                    extent = min.x + size.x + (span.skew * size.y).max(0.0);
+                    line.start = line.start.min(min.x + (span.skew * size.y).min(0.0));
+                    glyphs.push(Glyph {
+                        position: Rect::from_corners(min, min + size),
+                        glyph_id: item.glyph.id,
+                        font: item.font,
+                        color: span.color.to_linear().to_f32_array(),
+                        weight: span.weight,
+                        skew: span.skew,
+                    });
+                    glyph_gaps.push(line.gaps);
+                }
+                for (y, thickness) in &span_decorations[item.span] {
+                    segments.push(Segment {
+                        line: lines.len(),
+                        item: item_index,
+                        gap: line.gaps,
+                        x: pen_x..pen_x + advance,
+                        y: *y,
+                        thickness: *thickness,
+                        font: item.font,
+                        color: span.color.to_linear().to_f32_array(),
+                        texel_size: span.font_size / fonts[item.font].pixels_per_em() as f32,
+                        whitespace: item.whitespace,
+                    });
+                }
+                item_index += 1;
+                pen_x += advance;
+                if !item.whitespace {
+                    line.width = pen_x.max(extent);
+                }
+            }
+        }
+    }
+    lines.push(line.finish(glyphs.len(), false));
+
+    // glyphs with a negative skew may lean past the start of their line
+    let block_start = lines.iter().map(|line| line.start).fold(0.0, f32::min);
+    let block_end = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+    let mut baseline = 0.0;
+    // horizontal shift of each line and the additional width of each of its word gaps
+    let mut line_offsets = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            baseline -= line.metrics.height;
+        }
+        let free = block_end - line.width;
+        let (shift, gap_width) = match style.alignment {
+            TextAlignment::Left => (0.0, 0.0),
+            TextAlignment::Center => (
+                ((block_start + block_end) - (line.start + line.width)) * 0.5,
+                0.0,
+            ),
+            TextAlignment::Right => (free, 0.0),
+            TextAlignment::Justified if line.wrapped && line.gaps > 0 => {
+                (0.0, free / line.gaps as f32)
+            }
+            TextAlignment::Justified => (0.0, 0.0),
+        };
+        for (glyph, gap) in glyphs[line.glyphs.clone()]
+            .iter_mut()
+            .zip(&glyph_gaps[line.glyphs.clone()])
+        {
+            let offset = Vec2::new(shift + gap_width * *gap as f32, baseline);
+            glyph.position.min += offset;
+            glyph.position.max += offset;
+        }
+        line_offsets.push((shift, gap_width, baseline));
+    }
+
+    // merge the segments of adjacent glyphs, without the trailing whitespace of each line
+    let mut decorations: Vec<DecorationLine> = Vec::new();
+    for line_segments in segments.chunk_by(|a, b| a.line == b.line) {
+        let trailing = line_segments
+            .iter()
+            .rev()
+            .take_while(|segment| segment.whitespace)
+            .count();
+        // segments of the preceding glyph with the index of their decoration line
+        let mut previous: Vec<(usize, &Segment)> = Vec::new();
+        for item_segments in
+            line_segments[..line_segments.len() - trailing].chunk_by(|a, b| a.item == b.item)
+        {
+            let mut current = Vec::with_capacity(item_segments.len());
+            for segment in item_segments {
+                let (shift, gap_width, baseline) = line_offsets[segment.line];
+                let x = shift + gap_width * segment.gap as f32;
+                let continued = previous
+                    .iter()
+                    .find(|(_, other)| other.continues(segment))
+                    .map(|(index, _)| *index);
+                if let Some(index) = continued {
+                    decorations[index].position.max.x = segment.x.end + x;
+                    current.push((index, segment));
+                    continue;
+                }
+                let y = baseline + segment.y;
+                current.push((decorations.len(), segment));
+                decorations.push(DecorationLine {
+                    position: Rect::new(
+                        segment.x.start + x,
+                        y - segment.thickness / 2.0,
+                        segment.x.end + x,
+                        y + segment.thickness / 2.0,
+                    ),
+                    font: segment.font,
+                    color: segment.color,
+                    texel_size: segment.texel_size,
+                });
+            }
+            previous = current;
+        }
+    }
+
+    let first = lines.first().map(|line| line.metrics).unwrap_or_default();
+    let last = lines.last().map(|line| line.metrics).unwrap_or_default();
+    let mut bounds = Rect::new(
+        block_start,
+        baseline + last.descender,
+        block_end,
+        first.ascender,
+    );
+    let anchor = style.anchor.offset(bounds);
+    for glyph in glyphs.iter_mut() {
+        glyph.position.min -= anchor;
+        glyph.position.max -= anchor;
+    }
+    for decoration in decorations.iter_mut() {
+        decoration.position.min -= anchor;
+        decoration.position.max -= anchor;
+    }
+    bounds.min -= anchor;
+    bounds.max -= anchor;
+
+    TextLayout {
+        glyphs,
+        decorations,
+        glyph_ids: infos.into_keys().collect(),
+        line_count: lines.len(),
+        bounds,
+    }
+}
+
+/// Shapes `text` in runs, each using the first font of `chain` providing its code points.
+///
+/// A run continues in its font for as long as that font has the following code points, so
+/// e.g. spaces between fallback glyphs do not split the run.
+fn shape_with_fallback(fonts: &[Font], chain: &[usize], text: &str) -> Vec<(usize, ShapedGlyph)> {
+    let mut shaped = Vec::new();
+    let mut shape_run = |font: usize, range: Range<usize>| {
+        shaped.extend(
+            fonts[font]
+                .shape(&text[range.clone()])
+                .into_iter()
+                .map(|mut glyph| {
+                    glyph.cluster += range.start;
+                    (font, glyph)
+                }),
+        );
+    };
+
+    let mut run: Option<(usize, usize)> = None;
+    for (offset, character) in text.char_indices() {
+        let has_glyph = |font: &usize| fonts[*font].glyph_id(character).is_some();
+        let font = run
+            .map(|(font, _)| font)
+            .filter(has_glyph)
+            .or_else(|| chain.iter().copied().find(has_glyph))
+            // no font has the code point, use the .notdef glyph of the span's font
+            .unwrap_or(chain[0]);
+        match run {
+            Some((run_font, _)) if run_font == font => {}
+            Some((run_font, start)) => {
+                shape_run(run_font, start..offset);
+                run = Some((font, offset));
+            }
+            None => run = Some((font, offset)),
+        }
+    }
+    if let Some((font, start)) = run {
+        shape_run(font, start..text.len());
+    }
+    shaped
+}
+
+pub fn update_text_layout(
+    mut query: Query<(Ref<Text3d>, &mut TextMesh)>,
+    mut font_events: EventReader<AssetEvent<Font>>,
+    fonts: Res<Assets<Font>>,
+) {
+    // fonts that finished loading (or changed) since the last run require a new layout
+    let updated_fonts: HashSet<_> = font_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Added { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (text, mut text_mesh) in query.iter_mut() {
+        if text.is_changed() {
+            let span_fonts = text.span_fonts(text_mesh.family());
+            text_mesh.set_span_fonts(&span_fonts);
+        } else if !text_mesh
+            .fonts()
+            .iter()
+            .any(|font| updated_fonts.contains(&font.id()))
+        {
+            continue;
+        }
+        // wait until the whole fallback chain and the fonts of all spans are loaded
+        let Some(instances) = text_mesh
+            .fonts()
+            .iter()
+            .map(|font| {
+                fonts
+                    .get(font)
+                    .map(|font| font.instance(&text.style.variation))
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        let spans = text.layout_spans(text_mesh.fonts(), text_mesh.family().len());
+        let layout = layout_spans(&instances, &spans, &text.style);
+        text_mesh.set_variation(text.style.variation.clone());
+        text_mesh.set_weight(text.style.weight);
+        text_mesh.set_outline(text.style.outline);
+        text_mesh.set_shadow(text.style.shadow);
+        text_mesh.set_glow(text.style.glow);
+        for (font, glyph_id) in layout.glyph_ids {
+            text_mesh.add_missing(font, &[glyph_id]);
+        }
+        text_mesh.set_glyphs(layout.glyphs.into_boxed_slice());
+        text_mesh.set_decorations(layout.decorations.into_boxed_slice());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::test_font;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-5),
+            "{actual} is not {expected}"
+        );
+    }
+
+    fn layout(text: &str, style: Text3dStyle) -> TextLayout {
+        layout_text(&[test_font::font()], text, &style)
+    }
+
+    #[test]
+    fn breaks_lines_at_newlines() {
+        let layout = layout("aa\na", Text3dStyle::default());
+        assert_eq!(layout.line_count, 2);
+        assert_eq!(layout.glyphs.len(), 3);
+        assert_near(layout.glyphs[1].position.min, Vec2::new(0.55, 0.0));
+        assert_near(layout.glyphs[2].position.min, Vec2::new(0.05, -1.0));
+        assert_near(layout.bounds.min, Vec2::new(0.0, -1.2));
+        assert_near(layout.bounds.max, Vec2::new(1.0, 0.8));
+    }
+
+    #[test]
+    fn scales_with_font_size_and_line_spacing() {
+        let layout = layout(
+            "a\na",
+            Text3dStyle {
+                font_size: 2.0,
+                line_spacing: 1.5,
+                ..Default::default()
+            },
+        );
+        assert_near(layout.glyphs[0].position.max, Vec2::new(0.9, 1.0));
+        assert_near(layout.glyphs[1].position.min, Vec2::new(0.1, -3.0));
+    }
+
+    #[test]
+    fn wraps_words_exceeding_max_width() {
+        let layout = layout(
+            "aa aa aa",
+            Text3dStyle {
+                max_width: Some(1.2),
+                ..Default::default()
+            },
+        );
+        assert_eq!(layout.line_count, 3);
+        for (line, glyphs) in layout.glyphs.chunks(2).enumerate() {
+            assert_near(glyphs[0].position.min, Vec2::new(0.05, -(line as f32)));
+        }
+        // trailing whitespace hangs over the line end
+        assert_near(layout.bounds.max, Vec2::new(1.0, 0.8));
+    }
+
+    #[test]
+    fn keeps_words_longer_than_max_width() {
+        let layout = layout(
+            "aaaa",
+            Text3dStyle {
+                max_width: Some(1.0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(layout.line_count, 1);
+        assert_near(layout.bounds.max, Vec2::new(2.0, 0.8));
+    }
+
+    #[test]
+    fn aligns_lines_within_the_block() {
+        let line_start = |alignment| {
+            let layout = layout(
+                "aa\na",
+                Text3dStyle {
+                    alignment,
+                    ..Default::default()
+                },
+            );
+            layout.glyphs[2].position.min.x
+        };
+        assert!((line_start(TextAlignment::Left) - 0.05).abs() < 1e-5);
+        assert!((line_start(TextAlignment::Center) - 0.3).abs() < 1e-5);
+        assert!((line_start(TextAlignment::Right) - 0.55).abs() < 1e-5);
+    }
+
+    #[test]
+    fn justifies_wrapped_lines() {
+        let layout = layout(
+            "a a aaaa",
+            Text3dStyle {
+                max_width: Some(2.0),
+                alignment: TextAlignment::Justified,
+                ..Default::default()
+            },
+        );
+        assert_eq!(layout.line_count, 2);
+        // the only gap of the first line takes the remaining 0.5
+        assert_near(layout.glyphs[0].position.min, Vec2::new(0.05, 0.0));
+        assert_near(layout.glyphs[1].position.min, Vec2::new(1.55, 0.0));
+        // the last line of the paragraph is left aligned
+        assert_near(layout.glyphs[2].position.min, Vec2::new(0.05, -1.0));
+    }
+
+    #[test]
+    fn places_the_anchor_at_the_origin() {
+        let glyph_min = |anchor| {
+            let layout = layout(
+                "a",
+                Text3dStyle {
+                    anchor,
+                    ..Default::default()
+                },
+            );
+            assert_near(layout.bounds.size(), Vec2::new(0.5, 1.0));
+            layout.glyphs[0].position.min
+        };
+        assert_near(glyph_min(TextAnchor::BaselineLeft), Vec2::new(0.05, 0.0));
+        assert_near(glyph_min(TextAnchor::TopLeft), Vec2::new(0.05, -0.8));
+        assert_near(glyph_min(TextAnchor::Center), Vec2::new(-0.2, -0.3));
+        assert_near(glyph_min(TextAnchor::BottomRight), Vec2::new(-0.45, 0.2));
+        assert_near(glyph_min(TextAnchor::BaselineCenter), Vec2::new(-0.2, 0.0));
+    }
+
+    #[test]
+    fn includes_glyphs_leaning_past_the_line_start() {
+        let style = Text3dStyle {
+            skew: -0.2,
+            ..Default::default()
+        };
+        let glyph_min = |anchor| {
+            let layout = layout(
+                "a",
+                Text3dStyle {
+                    anchor,
+                    ..style.clone()
+                },
+            );
+            // the top of the glyph leans 0.1 to the left, 0.05 past the line start
+            assert_near(layout.bounds.size(), Vec2::new(0.55, 1.0));
+            layout.glyphs[0].position.min
+        };
+        assert_near(glyph_min(TextAnchor::BaselineLeft), Vec2::new(0.1, 0.0));
+        assert_near(glyph_min(TextAnchor::TopLeft), Vec2::new(0.1, -0.8));
+        assert_near(glyph_min(TextAnchor::BaselineRight), Vec2::new(-0.45, 0.0));
+
+        // the sheared extents of the lines are centered
+        let layout = layout(
+            "b\naa",
+            Text3dStyle {
+                alignment: TextAlignment::Center,
+                ..style
+            },
+        );
+        // the block starts 0.09 left of the line start at the top of `b`
+        assert_near(layout.bounds.size(), Vec2::new(1.09, 2.0));
+        assert_near(layout.glyphs[0].position.min, Vec2::new(0.39, 0.0));
+        assert_near(layout.glyphs[1].position.min, Vec2::new(0.12, -1.0));
+    }
+
+    fn underlined(color: Color) -> TextSpanStyle {
+        TextSpanStyle {
+            color: Some(color),
+            decoration: Some(TextDecoration {
+                underline: true,
+                strikethrough: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Lays out `text` with the test font as the family and the fallback font for spans
+    /// setting a font.
+    fn layout_text3d(text: &Text3d) -> TextLayout {
+        let handles = [Handle::weak_from_u128(1), Handle::weak_from_u128(2)];
+        let spans = text.layout_spans(&handles, 1);
+        let fonts = [test_font::font(), test_font::fallback()];
+        layout_spans(&fonts, &spans, &text.style)
+    }
+
+    #[test]
+    fn resolves_the_fallback_chain_of_each_span() {
+        let handles = [Handle::weak_from_u128(1), Handle::weak_from_u128(2)];
+        let text = Text3d::new("abc").with_span(Text3dSpan::new("ca").with_style(TextSpanStyle {
+            font: Some(handles[1].clone()),
+            ..Default::default()
+        }));
+        let spans = text.layout_spans(&handles, 1);
+        assert_eq!(spans[0].fonts, [0]);
+        assert_eq!(spans[1].fonts, [1, 0]);
+
+        // the family has no fallback for `c`, the span font misses `a`
+        let layout = layout_text3d(&text);
+        let fonts: Vec<_> = layout.glyphs.iter().map(|glyph| glyph.font).collect();
+        assert_eq!(fonts, [0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn lines_take_the_metrics_of_their_span_fonts() {
+        let text = Text3d::new("a\na").with_span(Text3dSpan::new(" c").with_style(TextSpanStyle {
+            font: Some(Handle::weak_from_u128(2)),
+            ..Default::default()
+        }));
+        let layout = layout_text3d(&text);
+        assert_eq!(layout.line_count, 2);
+        // the second line is as high as the taller span font
+        assert_near(layout.glyphs[1].position.min, Vec2::new(0.05, -1.2));
+        assert_near(layout.bounds.min, Vec2::new(0.0, -1.5));
+        assert_near(layout.bounds.max, Vec2::new(1.5, 0.8));
+    }
+
+    #[test]
+    fn merges_decorations_of_adjacent_glyphs_across_spans() {
+        let red = Color::srgb(1.0, 0.0, 0.0);
+        let text = Text3d::new("")
+            .with_span(Text3dSpan::new("aa").with_style(underlined(red)))
+            .with_span(Text3dSpan::new("aa ").with_style(underlined(red)))
+            .with_span(Text3dSpan::new("a"))
+            .with_span(Text3dSpan::new("a").with_style(underlined(red)))
+            .with_span(Text3dSpan::new("a").with_style(underlined(Color::WHITE)));
+        let layout = layout_text3d(&text);
+        let extents: Vec<_> = layout
+            .decorations
+            .iter()
+            .map(|decoration| (decoration.position.min.x, decoration.position.max.x))
+            .collect();
+        // glyphs are only merged with their direct neighbour of the same color
+        assert_eq!(extents, [(0.0, 2.5), (3.0, 3.5), (3.5, 4.0)]);
+        // the fallback underline metrics of the test font
+        for decoration in &layout.decorations {
+            assert!((decoration.position.min.y + 0.125).abs() < 1e-5);
+            assert!((decoration.position.max.y + 0.075).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn decorations_span_justified_gaps() {
+        let text = Text3d::new("a a aaaa").with_style(Text3dStyle {
+            max_width: Some(2.0),
+            alignment: TextAlignment::Justified,
+            decoration: TextDecoration {
+                underline: true,
+                strikethrough: true,
+            },
+            ..Default::default()
+        });
+        let layout = layout_text3d(&text);
+        assert_eq!(layout.line_count, 2);
+        let lines: Vec<_> = layout
+            .decorations
+            .iter()
+            .map(|decoration| (decoration.position.min, decoration.position.max.x))
+            .collect();
+        assert_eq!(lines.len(), 4);
+        // the underline and strikethrough of the first line cover the widened gap, without
+        // the trailing whitespace
+        for (index, (min, max_x)) in lines.iter().enumerate() {
+            let line = (index / 2) as f32;
+            let y = if index % 2 == 0 { -0.125 } else { 0.225 };
+            assert_near(*min, Vec2::new(0.0, y - line));
+            assert!((max_x - 2.0).abs() < 1e-5);
+        }
+    }
+}
@@ -4,20 +4,105 @@ use bevy::{
     reflect::TypePath,
     render::{
         mesh::MeshVertexAttribute,
-        render_resource::{AsBindGroup, ShaderRef, VertexFormat},
+        render_asset::RenderAssets,
+        render_resource::{AsBindGroup, AsBindGroupShaderType, ShaderRef, VertexFormat},
+        texture::GpuImage,
     },
 };
 
+use super::SdfMode;
+
 pub const ATTRIBUTE_TEXT_POSITION: MeshVertexAttribute =
     MeshVertexAttribute::new("Text_Position", 988540917, VertexFormat::Float32x2);
 
+/// Layer of the atlas texture array holding the glyph.
+pub const ATTRIBUTE_TEXT_LAYER: MeshVertexAttribute =
+    MeshVertexAttribute::new("Text_Layer", 988540918, VertexFormat::Uint32);
+
+/// Offset of the glyph contour, see [`super::TextMesh::set_weight`].
+pub const ATTRIBUTE_TEXT_WEIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Text_Weight", 988540919, VertexFormat::Float32);
+
+/// Layer of quads drawing a [`super::DecorationLine`], whose distance is computed in the
+/// shader instead of sampled from the atlas.
+pub const DECORATION_LAYER: u32 = u32::MAX;
+
 pub const SDF_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(98131239812464981);
 
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(SdfMaterialKey)]
+#[uniform(2, SdfMaterialUniform)]
 pub struct SdfMaterial {
-    #[texture(0)]
+    #[texture(0, dimension = "2d_array")]
     #[sampler(1)]
     pub sdf_texture: Handle<Image>,
+    pub mode: SdfMode,
+    /// Distance range of the atlas in texels.
+    pub range: f32,
+    /// Color of the outline around the glyphs, drawn in the same pass.
+    pub outline_color: LinearRgba,
+    /// Width of the outline as a fraction of the distance range outside the glyph outline,
+    /// from 0 to 1.
+    pub outline_width: f32,
+    /// Color of the drop shadow, transparent for none.
+    pub shadow_color: LinearRgba,
+    /// Offset of the drop shadow as a fraction of the distance range, with y pointing up.
+    pub shadow_offset: Vec2,
+    /// Width of the soft shadow edge as a fraction of the distance range.
+    pub shadow_softness: f32,
+    /// Color of the glow around the glyphs, transparent for none.
+    pub glow_color: LinearRgba,
+    /// Width of the glow as a fraction of the distance range outside the glyph outline.
+    pub glow_width: f32,
+}
+
+impl AsBindGroupShaderType<SdfMaterialUniform> for SdfMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<GpuImage>) -> SdfMaterialUniform {
+        SdfMaterialUniform {
+            outline_color: self.outline_color.to_vec4(),
+            shadow_color: self.shadow_color.to_vec4(),
+            glow_color: self.glow_color.to_vec4(),
+            shadow_offset: self.shadow_offset,
+            range: self.range,
+            outline_width: self.outline_width,
+            shadow_softness: self.shadow_softness,
+            glow_width: self.glow_width,
+        }
+    }
+}
+
+pub use uniform::SdfMaterialUniform;
+
+// the checks generated by the `ShaderType` derive are never called
+#[allow(dead_code)]
+mod uniform {
+    use bevy::{math::Vec2, math::Vec4, render::render_resource::ShaderType};
+
+    /// The effect parameters of [`super::SdfMaterial`] as laid out in the shader.
+    #[derive(Debug, Clone, Copy, Default, ShaderType)]
+    pub struct SdfMaterialUniform {
+        pub outline_color: Vec4,
+        pub shadow_color: Vec4,
+        pub glow_color: Vec4,
+        pub shadow_offset: Vec2,
+        pub range: f32,
+        pub outline_width: f32,
+        pub shadow_softness: f32,
+        pub glow_width: f32,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SdfMaterialKey {
+    mode: SdfMode,
+}
+
+impl From<&SdfMaterial> for SdfMaterialKey {
+    fn from(material: &SdfMaterial) -> Self {
+        Self {
+            mode: material.mode,
+        }
+    }
 }
 
 impl Material for SdfMaterial {
@@ -38,17 +123,30 @@ impl Material for SdfMaterial {
         _pipeline: &bevy::pbr::MaterialPipeline<Self>,
         descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
         layout: &bevy::render::mesh::MeshVertexBufferLayoutRef,
-        _key: bevy::pbr::MaterialPipelineKey<Self>,
+        key: bevy::pbr::MaterialPipelineKey<Self>,
     ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
         // TODO: store position/uv/color per char in SBO, instead of per vertex
         let vertex_layout = layout.0.get_layout(&[
             ATTRIBUTE_TEXT_POSITION.at_shader_location(0),
             Mesh::ATTRIBUTE_UV_0.at_shader_location(1),
             Mesh::ATTRIBUTE_COLOR.at_shader_location(2),
+            ATTRIBUTE_TEXT_LAYER.at_shader_location(3),
+            ATTRIBUTE_TEXT_WEIGHT.at_shader_location(4),
         ])?;
 
         descriptor.vertex.buffers = vec![vertex_layout];
         descriptor.primitive.cull_mode = None;
+
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            match key.bind_group_data.mode {
+                SdfMode::Sdf => {}
+                SdfMode::Msdf => fragment.shader_defs.push("MSDF".into()),
+                SdfMode::Mtsdf => {
+                    fragment.shader_defs.push("MSDF".into());
+                    fragment.shader_defs.push("MTSDF".into());
+                }
+            }
+        }
         Ok(())
     }
 }
@@ -4,13 +4,25 @@ use bevy::prelude::{App, IntoSystemConfigs, Shader};
 use bevy::{app::Plugin, pbr::MaterialPlugin};
 use material::SdfMaterial;
 
+mod layout;
 mod material;
 mod text_mesh;
 
 pub use super::font::Font;
 pub use super::font::FontAtlases;
+pub use super::font::FontFamily;
+pub use super::font::FontVariation;
+pub use super::font::GlyphId;
+pub use super::font::SdfMode;
+pub use super::font::ShapedGlyph;
+pub use layout::{layout_spans, layout_text, LayoutSpan, TextLayout};
+pub use layout::{Text3d, Text3dSpan, Text3dStyle, TextAlignment, TextAnchor};
+pub use layout::{TextDecoration, TextSpanStyle};
+pub use text_mesh::DecorationLine;
 pub use text_mesh::Glyph;
 pub use text_mesh::TextMesh;
+pub use text_mesh::TextMeshComplete;
+pub use text_mesh::{TextGlow, TextOutline, TextShadow};
 
 pub struct TextMeshPlugin;
 
@@ -25,10 +37,16 @@ impl Plugin for TextMeshPlugin {
         );
 
         app.add_plugins(MaterialPlugin::<SdfMaterial>::default())
+            .add_event::<TextMeshComplete>()
             .add_systems(
                 PostUpdate,
                 (
+                    text_mesh::reset_modified_fonts,
+                    layout::update_text_layout,
                     text_mesh::update_font_atlases,
+                    text_mesh::update_glyph_users,
+                    text_mesh::evict_unused_glyphs,
+                    text_mesh::update_pending_glyphs,
                     text_mesh::create_atlas_meshes,
                     text_mesh::update_text_mesh,
                 )
@@ -6,12 +6,16 @@ struct Vertex {
     @location(0) position: vec2<f32>,
     @location(1) uv: vec2<f32>,
     @location(2) color: vec4<f32>,
+    @location(3) layer: u32,
+    @location(4) weight: f32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) uv: vec2<f32>,
     @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) layer: u32,
+    @location(3) @interpolate(flat) weight: f32,
 };
 
 @vertex
@@ -23,28 +27,73 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     );
     out.uv = vertex.uv;
     out.color = vertex.color;
+    out.layer = vertex.layer;
+    out.weight = vertex.weight;
     return out;
 }
 
 
 
-@group(2) @binding(0) var material_sdf_texture: texture_2d<f32>;
+@group(2) @binding(0) var material_sdf_texture: texture_2d_array<f32>;
 @group(2) @binding(1) var material_sdf_sampler: sampler;
 
+struct SdfMaterialUniform {
+    outline_color: vec4<f32>,
+    shadow_color: vec4<f32>,
+    glow_color: vec4<f32>,
+    shadow_offset: vec2<f32>,
+    range: f32,
+    outline_width: f32,
+    shadow_softness: f32,
+    glow_width: f32,
+};
+
+@group(2) @binding(2) var<uniform> material: SdfMaterialUniform;
+
+// layer of decoration lines, see `DECORATION_LAYER`
+const DECORATION_LAYER: u32 = 0xffffffffu;
+
 fn median(a: f32, b: f32, c: f32) -> f32 {
     return max(min(a, b), min(max(a, b), c));
 }
 
+// distance to the glyph contour, 0.5 on the edge
+fn sdf_distance(sample: vec4<f32>) -> f32 {
+#ifdef MSDF
+    return median(sample.r, sample.g, sample.b);
+#else
+    return sample.r;
+#endif
+}
+
+// distance used for effects outside the glyph, MTSDF stores the true distance in alpha
+fn sdf_outer_distance(sample: vec4<f32>) -> f32 {
+#ifdef MTSDF
+    return sample.a;
+#else
+    return sdf_distance(sample);
+#endif
+}
+
+// distance of decoration lines, their uv holds the half thickness and the vertical offset from
+// the line center in texels
+fn decoration_distance(uv: vec2<f32>) -> f32 {
+    return 0.5 + (uv.x - abs(uv.y)) / material.range;
+}
+
+fn contour(d: f32, edge: f32, w: f32) -> f32 {
+    return smoothstep(edge - w, edge + w, d);
+}
 
-fn contour(d: f32, w: f32) -> f32 {
-    return smoothstep(0.5 - w, 0.5 + w, d);
+// draws a layer with straight alpha over premultiplied `dst`
+fn blend_over(dst: vec4<f32>, color: vec3<f32>, alpha: f32) -> vec4<f32> {
+    return vec4(color * alpha, alpha) + dst * (1.0 - alpha);
 }
 
-fn samp(uv: vec2<f32>, w: f32) -> f32 {
-    let sample = textureSample(material_sdf_texture, material_sdf_sampler, uv);
-    // let dist = median(sample.r, sample.g, sample.b);
-    let dist = sample.a;
-    return contour(dist, w);
+fn samp(uv: vec2<f32>, layer: u32, w: f32) -> f32 {
+    let sample = textureSample(material_sdf_texture, material_sdf_sampler, uv, layer);
+    let dist = sdf_distance(sample);
+    return contour(dist, 0.5, w);
 }
 
 fn safeNormalize(v: vec2<f32>) -> vec2<f32> {
@@ -69,17 +118,55 @@ fn fragment(
     // return vec4(1, 1, 1, opacity);
 
     // adapted from: https://jvm-gaming.org/t/solved-signed-distance-field-fonts-look-crappy-at-small-pt-sizes/49617/7
-    let sample = textureSample(material_sdf_texture, material_sdf_sampler, mesh.uv);
-    // let dist = median(sample.r, sample.g, sample.b);
-    let dist = sample.a;
+    // decorations sample the first layer to keep the control flow uniform, the result is unused
+    let is_decoration = mesh.layer == DECORATION_LAYER;
+    let layer = select(mesh.layer, 0u, is_decoration);
+    let sample = textureSample(material_sdf_texture, material_sdf_sampler, mesh.uv, layer);
+    let dist = select(sdf_distance(sample), decoration_distance(mesh.uv), is_decoration);
     let width = fwidth(dist);
-    var alpha = contour(dist, width) ;
+    // the weight moves the contour outwards for bolder and inwards for thinner glyphs
+    let edge = 0.5 - 0.5 * mesh.weight;
+    var alpha = contour(dist, edge, width) ;
     // let dscale = 0.354; // 0.354; // half of 1/sqrt2; you can play with this
     // let duv = dscale * (dpdx(mesh.uv) + dpdy(mesh.uv));
     // let box = vec4(mesh.uv - duv, mesh.uv + duv);
-    // let asum = samp(box.xy, width) + samp(box.zw, width) + samp(box.xw, width) + samp(box.zy, width);
+    // let asum = samp(box.xy, mesh.layer, width) + samp(box.zw, mesh.layer, width) + samp(box.xw, mesh.layer, width) + samp(box.zy, mesh.layer, width);
     // alpha = (alpha + 0.5 * asum) / 3.0;
-    return vec4(mesh.color.rgb, alpha);
+    // effects outside the glyph, each is transparent when disabled
+    let outer_dist = select(sdf_outer_distance(sample), dist, is_decoration);
+    let outer_width = fwidth(outer_dist);
+    // the outline extends the contour outwards
+    let outline_edge = edge - 0.5 * max(material.outline_width, 0.0);
+    var outline = 0.0;
+    if material.outline_width > 0.0 {
+        outline = smoothstep(outline_edge - outer_width, outline_edge + outer_width, outer_dist)
+            * material.outline_color.a;
+    }
+    // the glow fades out over its width beyond the outline
+    var glow = 0.0;
+    if material.glow_width > 0.0 {
+        let glow_edge = outline_edge - 0.5 * material.glow_width;
+        glow = smoothstep(glow_edge, outline_edge, outer_dist) * material.glow_color.a;
+    }
+    // the shadow is the glyph and outline shape moved by the offset, in texels of the atlas
+    let texel_offset = material.shadow_offset * 0.5 * material.range;
+    let shadow_uv = mesh.uv - texel_offset / vec2<f32>(textureDimensions(material_sdf_texture));
+    let shadow_sample = textureSample(material_sdf_texture, material_sdf_sampler, shadow_uv, layer);
+    let shadow_dist = select(
+        sdf_outer_distance(shadow_sample),
+        decoration_distance(mesh.uv - vec2(0.0, texel_offset.y)),
+        is_decoration,
+    );
+    let shadow_width = max(0.5 * material.shadow_softness, outer_width);
+    let shadow = smoothstep(outline_edge - shadow_width, outline_edge + shadow_width, shadow_dist)
+        * material.shadow_color.a;
+
+    var color = vec4(0.0);
+    color = blend_over(color, material.shadow_color.rgb, shadow);
+    color = blend_over(color, material.glow_color.rgb, glow);
+    color = blend_over(color, material.outline_color.rgb, outline);
+    color = blend_over(color, mesh.color.rgb, alpha);
+    return vec4(color.rgb / max(color.a, 1e-4), color.a);
 
     // adapted from Cinder: https://github.com/paulhoux/Cinder-SDFText/blob/565b24e0d886ac6b8dbccdeed0d9a9d4bec3d45b/src/cinder/gl/SdfText.cpp
     // let texSize = textureDimensions(material_sdf_texture, 0);
@@ -1,17 +1,24 @@
-use bevy::asset::{AssetId, Assets};
-use bevy::math::Rect;
+use bevy::asset::{AssetEvent, AssetId, Assets};
+use bevy::color::Color;
+use bevy::math::{Rect, Vec2};
 use bevy::pbr::MaterialMeshBundle;
-use bevy::prelude::{BuildChildren, Changed, Commands, Entity, Image, Query, Res, ResMut};
+use bevy::prelude::{
+    BuildChildren, Changed, Commands, DespawnRecursiveExt, DetectChangesMut, Entity, Event,
+    EventReader, EventWriter, Image, Query, RemovedComponents, Res, ResMut,
+};
 use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
 use bevy::render::render_asset::RenderAssetUsages;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy::{
     asset::Handle,
     prelude::{Component, Mesh},
 };
 
-use super::material::{SdfMaterial, ATTRIBUTE_TEXT_POSITION};
-use super::{Font, FontAtlases};
+use super::material::{
+    SdfMaterial, ATTRIBUTE_TEXT_LAYER, ATTRIBUTE_TEXT_POSITION, ATTRIBUTE_TEXT_WEIGHT,
+    DECORATION_LAYER,
+};
+use super::{Font, FontAtlases, FontFamily, FontVariation, GlyphId};
 
 pub struct PositionedGlyph {
     pub position: Rect,
@@ -20,62 +27,418 @@ pub struct PositionedGlyph {
     pub color: [f32; 4],
 }
 
+#[derive(Debug, Clone)]
 pub struct Glyph {
     pub position: Rect,
-    pub character: char,
+    pub glyph_id: GlyphId,
+    /// Index into [`TextMesh::fonts`] of the font providing this glyph.
+    pub font: usize,
+    pub color: [f32; 4],
+    /// Weight offset added to the one of the [`TextMesh`], see [`TextMesh::set_weight`].
+    pub weight: f32,
+    /// Horizontal shift per unit of height for a synthetic italic, the tangent of the slant
+    /// angle. The quad is sheared around the bottom of `position`, which layout places so
+    /// that the shear pivots on the baseline.
+    pub skew: f32,
+}
+
+/// A horizontal line along the glyphs, e.g. an underline.
+#[derive(Debug, Clone)]
+pub struct DecorationLine {
+    /// Extent of the line, its height is the thickness.
+    pub position: Rect,
+    /// Index into [`TextMesh::fonts`] of the font whose mesh draws the line, with the font's
+    /// effects.
+    pub font: usize,
     pub color: [f32; 4],
+    /// Size of an atlas texel of the font in world units.
+    pub texel_size: f32,
+}
+
+/// An outline around the glyphs, rendered from the distance field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextOutline {
+    /// Width as a fraction of the distance range outside the glyph outline, from 0 to 1. The
+    /// widest outline is limited by the font's `range`.
+    pub width: f32,
+    pub color: Color,
+}
+
+impl Default for TextOutline {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            color: Color::BLACK,
+        }
+    }
+}
+
+/// A drop shadow behind the glyphs and their outline.
+///
+/// Glyphs are drawn in order, a shadow reaching into the previous glyph is drawn over it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    /// Offset as a fraction of the distance range, with y pointing up. Together with the
+    /// softness it should stay below 1, the shadow is cut off at the glyph quads otherwise.
+    pub offset: Vec2,
+    /// Width of the soft shadow edge as a fraction of the distance range, 0 for a hard edge.
+    pub softness: f32,
+    pub color: Color,
+}
+
+impl Default for TextShadow {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::new(0.3, -0.3),
+            softness: 0.2,
+            color: Color::srgba(0.0, 0.0, 0.0, 0.8),
+        }
+    }
 }
 
+/// A glow around the glyphs and their outline, fading out over its width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextGlow {
+    /// Width as a fraction of the distance range outside the glyph outline, from 0 to 1.
+    pub width: f32,
+    pub color: Color,
+}
+
+impl Default for TextGlow {
+    fn default() -> Self {
+        Self {
+            width: 0.5,
+            color: Color::WHITE,
+        }
+    }
+}
+
+/// Sent once all glyphs of a [`TextMesh`] are generated and part of its meshes.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TextMeshComplete {
+    pub entity: Entity,
+}
+
+/// The meshes of a laid out text, one per font.
+///
+/// With a [`Text3d`](super::Text3d) on the same entity, its [`Text3dStyle`](super::Text3dStyle)
+/// is the source of truth for the variation, weight and effects, they are set from it on every
+/// layout.
 #[derive(Component)]
 pub struct TextMesh {
-    font: Handle<Font>,
-    missing: Vec<char>,
+    /// The primary font followed by its fallbacks, then the fonts of text spans.
+    fonts: Vec<Handle<Font>>,
+    // number of fonts of the family, the fonts of spans follow
+    family_len: usize,
+    variation: FontVariation,
+    outline: TextOutline,
+    shadow: Option<TextShadow>,
+    glow: Option<TextGlow>,
+    weight: f32,
+    missing: Vec<(usize, GlyphId)>,
     glyphs: Box<[Glyph]>,
+    decorations: Box<[DecorationLine]>,
+    // keyed by font index, all atlases of a font are layers of one texture
     meshes: HashMap<usize, Handle<Mesh>>,
     child_entities: HashMap<usize, Entity>,
+    stale_entities: Vec<Entity>,
+    complete: bool,
 }
 
 impl TextMesh {
     pub fn new(font: Handle<Font>) -> Self {
+        Self::from_family(&FontFamily::new(font))
+    }
+
+    /// Creates a text mesh using the fonts of `family`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the family has no fonts.
+    pub fn from_family(family: &FontFamily) -> Self {
+        assert!(
+            !family.fonts.is_empty(),
+            "a font family needs at least its primary font"
+        );
         Self {
-            font,
+            fonts: family.fonts.clone(),
+            family_len: family.fonts.len(),
+            variation: Default::default(),
+            outline: Default::default(),
+            shadow: None,
+            glow: None,
+            weight: 0.0,
             missing: Default::default(),
             glyphs: Default::default(),
+            decorations: Default::default(),
             meshes: Default::default(),
             child_entities: Default::default(),
+            stale_entities: Default::default(),
+            complete: false,
         }
     }
 
+    pub fn with_fallback(mut self, font: Handle<Font>) -> Self {
+        self.fonts.insert(self.family_len, font);
+        self.family_len += 1;
+        self
+    }
+
     pub fn font_id(&self) -> AssetId<Font> {
-        self.font.id()
+        self.fonts[0].id()
     }
 
-    pub fn add_missing(&mut self, missing: &[char]) {
-        self.missing.extend_from_slice(missing);
+    pub fn fonts(&self) -> &[Handle<Font>] {
+        &self.fonts
+    }
+
+    /// The primary font and its fallbacks, without the fonts of spans.
+    pub fn family(&self) -> &[Handle<Font>] {
+        &self.fonts[..self.family_len]
+    }
+
+    /// Sets the fonts used by text spans in addition to the family, they follow the family in
+    /// [`TextMesh::fonts`].
+    pub fn set_span_fonts(&mut self, fonts: &[Handle<Font>]) {
+        if self.fonts[self.family_len..] != *fonts {
+            // the atlas meshes are keyed by font index
+            for index in self.family_len..self.fonts.len() {
+                self.meshes.remove(&index);
+                self.stale_entities
+                    .extend(self.child_entities.remove(&index));
+            }
+            self.fonts.truncate(self.family_len);
+            self.fonts.extend_from_slice(fonts);
+            // glyphs of span fonts refer to the previous list, they are laid out again once
+            // the new fonts are loaded
+            self.glyphs = Default::default();
+            self.decorations = Default::default();
+            let family_len = self.family_len;
+            self.missing.retain(|(font, _)| *font < family_len);
+        }
+    }
+
+    /// Sets the variable font axis coordinates used for this text, on top of the font's own.
+    pub fn set_variation(&mut self, variation: FontVariation) {
+        if self.variation != variation {
+            self.variation = variation;
+            // glyphs of the previous instance live in other atlases
+            self.despawn_meshes();
+        }
+    }
+
+    pub fn variation(&self) -> &FontVariation {
+        &self.variation
+    }
+
+    pub fn set_outline(&mut self, outline: TextOutline) {
+        if self.outline != outline {
+            self.outline = outline;
+            // effects are part of the materials
+            self.despawn_meshes();
+        }
+    }
+
+    pub fn outline(&self) -> &TextOutline {
+        &self.outline
+    }
+
+    pub fn set_shadow(&mut self, shadow: Option<TextShadow>) {
+        if self.shadow != shadow {
+            self.shadow = shadow;
+            self.despawn_meshes();
+        }
+    }
+
+    pub fn shadow(&self) -> Option<&TextShadow> {
+        self.shadow.as_ref()
+    }
+
+    pub fn set_glow(&mut self, glow: Option<TextGlow>) {
+        if self.glow != glow {
+            self.glow = glow;
+            self.despawn_meshes();
+        }
+    }
+
+    pub fn glow(&self) -> Option<&TextGlow> {
+        self.glow.as_ref()
+    }
+
+    /// Sets the weight offset as a fraction of the distance range, moving the glyph contour
+    /// outwards for a synthetic bold or inwards for thinner glyphs, from -1 to 1.
+    pub fn set_weight(&mut self, weight: f32) {
+        // part of the vertices, unlike the effects the materials are kept
+        self.weight = weight;
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Despawns all atlas meshes, they are created again with the current fonts and effects.
+    fn despawn_meshes(&mut self) {
+        self.meshes.clear();
+        for (_, child) in self.child_entities.drain() {
+            self.stale_entities.push(child);
+        }
+    }
+
+    /// Requests atlas entries for glyphs of the font at index `font` of [`TextMesh::fonts`].
+    pub fn add_missing(&mut self, font: usize, missing: &[GlyphId]) {
+        self.missing
+            .extend(missing.iter().map(|glyph_id| (font, *glyph_id)));
+    }
+
+    /// Despawns the atlas mesh of the font at index `font` and requests its glyphs again,
+    /// e.g. after its atlases were replaced.
+    fn reset_font(&mut self, font: usize) {
+        self.meshes.remove(&font);
+        self.stale_entities
+            .extend(self.child_entities.remove(&font));
+        let glyph_ids: Vec<_> = self
+            .glyphs
+            .iter()
+            .filter(|glyph| glyph.font == font)
+            .map(|glyph| (font, glyph.glyph_id))
+            .collect();
+        self.missing.extend(glyph_ids);
     }
 
     pub fn set_glyphs(&mut self, glyphs: Box<[Glyph]>) {
         self.glyphs = glyphs;
     }
+
+    pub fn set_decorations(&mut self, decorations: Box<[DecorationLine]>) {
+        self.decorations = decorations;
+    }
+
+    /// Whether all glyphs are generated, otherwise only the available ones are rendered.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Rebuilds text meshes using modified fonts, e.g. after a hot reload, with glyphs from the
+/// regenerated atlases.
+pub fn reset_modified_fonts(
+    mut query: Query<&mut TextMesh>,
+    mut font_events: EventReader<AssetEvent<Font>>,
+) {
+    let modified_fonts: HashSet<_> = font_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified_fonts.is_empty() {
+        return;
+    }
+    for mut text_mesh in query.iter_mut() {
+        let modified: Vec<_> = (0..text_mesh.fonts.len())
+            .filter(|index| modified_fonts.contains(&text_mesh.fonts[*index].id()))
+            .collect();
+        for index in modified {
+            text_mesh.reset_font(index);
+        }
+    }
 }
 
 pub fn update_font_atlases(
     mut query: Query<&mut TextMesh, Changed<TextMesh>>,
     mut atlases: ResMut<FontAtlases>,
-    mut textures: ResMut<Assets<Image>>,
     fonts: Res<Assets<Font>>,
 ) {
     for mut text_mesh in query.iter_mut() {
-        atlases.add_code_points(
-            &text_mesh.missing,
-            text_mesh.font_id(),
-            &fonts,
-            &mut textures,
-        );
+        if text_mesh.missing.is_empty() {
+            continue;
+        }
+        for (index, font) in text_mesh.fonts.iter().enumerate() {
+            let missing: Vec<_> = text_mesh
+                .missing
+                .iter()
+                .filter(|(font, _)| *font == index)
+                .map(|(_, glyph_id)| *glyph_id)
+                .collect();
+            if !missing.is_empty() {
+                atlases.add_glyphs(&missing, font.id(), &text_mesh.variation, &fonts);
+            }
+        }
         text_mesh.missing.clear();
     }
 }
 
+/// Retains the glyphs of each text mesh in the [`FontAtlases`], so they are not evicted.
+pub fn update_glyph_users(
+    query: Query<(Entity, &TextMesh), Changed<TextMesh>>,
+    mut removed: RemovedComponents<TextMesh>,
+    mut atlases: ResMut<FontAtlases>,
+) {
+    for entity in removed.read() {
+        atlases.release_glyph_users(entity);
+    }
+    for (entity, text_mesh) in query.iter() {
+        let glyphs: HashSet<_> = text_mesh
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.font, glyph.glyph_id))
+            .collect();
+        atlases.set_glyph_users(
+            entity,
+            glyphs.into_iter().map(|(font, glyph_id)| {
+                (
+                    text_mesh.fonts[font].id(),
+                    text_mesh.variation.clone(),
+                    glyph_id,
+                )
+            }),
+        );
+    }
+}
+
+pub fn evict_unused_glyphs(
+    mut query: Query<&mut TextMesh>,
+    mut atlases: ResMut<FontAtlases>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    let evicted_fonts = atlases.evict_unused(&mut textures);
+    if evicted_fonts.is_empty() {
+        return;
+    }
+    for mut text_mesh in query.iter_mut() {
+        if text_mesh
+            .fonts
+            .iter()
+            .any(|font| evicted_fonts.contains(&font.id()))
+        {
+            // glyphs moved within the repacked atlases
+            text_mesh.set_changed();
+        }
+    }
+}
+
+pub fn update_pending_glyphs(
+    mut query: Query<&mut TextMesh>,
+    mut atlases: ResMut<FontAtlases>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    let updated_fonts = atlases.poll_pending(&mut textures);
+    if updated_fonts.is_empty() {
+        return;
+    }
+    for mut text_mesh in query.iter_mut() {
+        if text_mesh
+            .fonts
+            .iter()
+            .any(|font| updated_fonts.contains(&font.id()))
+        {
+            // rebuild the meshes to include the new glyphs
+            text_mesh.set_changed();
+        }
+    }
+}
+
 pub fn create_atlas_meshes(
     mut query: Query<(Entity, &mut TextMesh), Changed<TextMesh>>,
     mut commands: Commands,
@@ -84,59 +447,166 @@ pub fn create_atlas_meshes(
     mut materials: ResMut<Assets<SdfMaterial>>,
 ) {
     for (entity, mut text_mesh) in query.iter_mut() {
-        if let Some(data) = font_atlas.data(text_mesh.font.id()) {
-            // TODO only create necessary atlas meshes
-            for i in 0..data.atlas_count() {
-                if text_mesh.meshes.contains_key(&i) {
-                    continue;
-                }
-
-                let mesh = meshes.add(Mesh::new(
-                    PrimitiveTopology::TriangleList,
-                    RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-                ));
-                text_mesh.meshes.insert(i, mesh.clone());
-                let child = commands
-                    .spawn((MaterialMeshBundle {
-                        mesh: mesh,
-                        material: materials.add(SdfMaterial {
-                            sdf_texture: data.atlas_texture(i).unwrap(),
-                        }),
-                        ..Default::default()
-                    },))
-                    .set_parent(entity)
-                    .id();
-                text_mesh.child_entities.insert(i, child);
+        let text_mesh = text_mesh.as_mut();
+        // atlases are freed with their font
+        let freed: Vec<_> = text_mesh
+            .meshes
+            .keys()
+            .filter(|font_index| {
+                font_atlas
+                    .data(text_mesh.fonts[**font_index].id(), &text_mesh.variation)
+                    .is_none()
+            })
+            .copied()
+            .collect();
+        for key in freed {
+            text_mesh.meshes.remove(&key);
+            text_mesh
+                .stale_entities
+                .extend(text_mesh.child_entities.remove(&key));
+        }
+        for child in text_mesh.stale_entities.drain(..) {
+            commands.entity(child).despawn_recursive();
+        }
+        for (font_index, font) in text_mesh.fonts.iter().enumerate() {
+            if text_mesh.meshes.contains_key(&font_index) {
+                continue;
             }
+            let Some(data) = font_atlas.data(font.id(), &text_mesh.variation) else {
+                continue;
+            };
+            // the texture is created with the first glyph
+            let Some(texture) = data.texture() else {
+                continue;
+            };
+
+            // disabled effects are transparent
+            let shadow = text_mesh.shadow.unwrap_or(TextShadow {
+                color: Color::NONE,
+                ..Default::default()
+            });
+            let glow = text_mesh.glow.unwrap_or(TextGlow {
+                width: 0.0,
+                color: Color::NONE,
+            });
+            let mesh = meshes.add(Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+            ));
+            text_mesh.meshes.insert(font_index, mesh.clone());
+            let child = commands
+                .spawn((MaterialMeshBundle {
+                    mesh,
+                    material: materials.add(SdfMaterial {
+                        sdf_texture: texture,
+                        mode: data.mode(),
+                        range: data.range() as f32,
+                        outline_color: text_mesh.outline.color.to_linear(),
+                        outline_width: text_mesh.outline.width.clamp(0.0, 1.0),
+                        shadow_color: shadow.color.to_linear(),
+                        shadow_offset: shadow.offset,
+                        shadow_softness: shadow.softness.max(0.0),
+                        glow_color: glow.color.to_linear(),
+                        glow_width: glow.width.clamp(0.0, 1.0),
+                    }),
+                    ..Default::default()
+                },))
+                .set_parent(entity)
+                .id();
+            text_mesh.child_entities.insert(font_index, child);
         }
     }
 }
 
 pub fn update_text_mesh(
-    mut query: Query<&TextMesh, Changed<TextMesh>>,
+    mut query: Query<(Entity, &mut TextMesh), Changed<TextMesh>>,
     mut meshes: ResMut<Assets<Mesh>>,
+    mut complete_events: EventWriter<TextMeshComplete>,
     font_atlas: Res<FontAtlases>,
 ) {
-    for text_mesh in query.iter_mut() {
-        // bevy::log::info!("Regenerate");
-        let Some(data) = font_atlas.data(text_mesh.font.id()) else {
-            continue;
-        };
+    for (entity, mut text_mesh) in query.iter_mut() {
+        let complete = text_mesh.missing.is_empty()
+            && text_mesh.glyphs.iter().all(|glyph| {
+                font_atlas
+                    .data(text_mesh.fonts[glyph.font].id(), &text_mesh.variation)
+                    .is_some_and(|data| {
+                        data.has_glyph(glyph.glyph_id) && !data.is_pending(glyph.glyph_id)
+                    })
+            });
+        if complete != text_mesh.complete {
+            // not a change of the text itself
+            text_mesh.bypass_change_detection().complete = complete;
+            if complete {
+                complete_events.send(TextMeshComplete { entity });
+            }
+        }
 
-        for (index, mesh) in text_mesh.meshes.iter() {
+        // bevy::log::info!("Regenerate");
+        for (font_index, mesh) in text_mesh.meshes.iter() {
+            let Some(data) =
+                font_atlas.data(text_mesh.fonts[*font_index].id(), &text_mesh.variation)
+            else {
+                continue;
+            };
             if let Some(mesh) = meshes.get_mut(mesh) {
                 let mut builder = TextMeshBuilder::new(mesh);
-                for glyph in text_mesh.glyphs.iter().filter(|glyph| {
-                    data.atlas(glyph.character)
-                        .map(|atlas| atlas == *index)
-                        .unwrap_or(false)
-                }) {
-                    if let Some(atlas_rect) = data
-                        .glyph_info(glyph.character)
-                        .and_then(|info| data.lookup_glyph(info.id))
-                    {
-                        builder.append_glyph(&glyph.position, &atlas_rect, &glyph.color);
-                    }
+                for glyph in text_mesh
+                    .glyphs
+                    .iter()
+                    .filter(|glyph| glyph.font == *font_index)
+                {
+                    let (Some(layer), Some(rect)) =
+                        (data.atlas(glyph.glyph_id), data.glyph_rect(glyph.glyph_id))
+                    else {
+                        continue;
+                    };
+                    // extend the quad into the distance range around the outline, which
+                    // holds the outline, shadow and glow
+                    let rect = rect.as_rect();
+                    let texel_size = glyph.position.size() / rect.size().max(Vec2::ONE);
+                    let padding = data.range() as f32 / 2.0;
+                    let position = Rect {
+                        min: glyph.position.min - padding * texel_size,
+                        max: glyph.position.max + padding * texel_size,
+                    };
+                    let size_inv = 1.0 / data.layer_size().as_vec2();
+                    let uv = Rect::from_corners(
+                        (rect.min - padding) * size_inv,
+                        (rect.max + padding) * size_inv,
+                    );
+                    let shear = glyph.skew
+                        * Vec2::new(
+                            position.min.y - glyph.position.min.y,
+                            position.max.y - glyph.position.min.y,
+                        );
+                    let weight = (text_mesh.weight + glyph.weight).clamp(-1.0, 1.0);
+                    builder.append_glyph(&position, shear, &uv, layer as u32, &glyph.color, weight);
+                }
+                for decoration in text_mesh
+                    .decorations
+                    .iter()
+                    .filter(|decoration| decoration.font == *font_index)
+                {
+                    // the uv holds the half thickness and the vertical offset from the
+                    // center in texels, the shader computes the distance from them
+                    let padding = data.range() as f32 / 2.0;
+                    let half_thickness = decoration.position.height() / 2.0 / decoration.texel_size;
+                    let position = Rect {
+                        min: decoration.position.min - Vec2::Y * padding * decoration.texel_size,
+                        max: decoration.position.max + Vec2::Y * padding * decoration.texel_size,
+                    };
+                    let uv = Rect {
+                        min: Vec2::new(half_thickness, -half_thickness - padding),
+                        max: Vec2::new(half_thickness, half_thickness + padding),
+                    };
+                    builder.append_glyph(
+                        &position,
+                        Vec2::ZERO,
+                        &uv,
+                        DECORATION_LAYER,
+                        &decoration.color,
+                        text_mesh.weight.clamp(-1.0, 1.0),
+                    );
                 }
             }
         }
@@ -173,6 +643,15 @@ impl<'a> TextMeshBuilder<'a> {
                 VertexAttributeValues::Float32x4(vec![]),
             );
         }
+        if !mesh.contains_attribute(ATTRIBUTE_TEXT_LAYER) {
+            mesh.insert_attribute(ATTRIBUTE_TEXT_LAYER, VertexAttributeValues::Uint32(vec![]));
+        }
+        if !mesh.contains_attribute(ATTRIBUTE_TEXT_WEIGHT) {
+            mesh.insert_attribute(
+                ATTRIBUTE_TEXT_WEIGHT,
+                VertexAttributeValues::Float32(vec![]),
+            );
+        }
         if !mesh.indices().is_some() {
             mesh.insert_indices(Indices::U32(vec![]));
         }
@@ -192,6 +671,16 @@ impl<'a> TextMeshBuilder<'a> {
         {
             colors.clear();
         }
+        if let Some(VertexAttributeValues::Uint32(layers)) =
+            mesh.attribute_mut(ATTRIBUTE_TEXT_LAYER)
+        {
+            layers.clear();
+        }
+        if let Some(VertexAttributeValues::Float32(weights)) =
+            mesh.attribute_mut(ATTRIBUTE_TEXT_WEIGHT)
+        {
+            weights.clear();
+        }
         if let Some(Indices::U32(indices)) = mesh.indices_mut() {
             indices.clear();
         }
@@ -199,15 +688,24 @@ impl<'a> TextMeshBuilder<'a> {
         Self { index: 0, mesh }
     }
 
-    fn append_glyph(&mut self, position: &Rect, uv: &Rect, color: &[f32; 4]) {
+    fn append_glyph(
+        &mut self,
+        position: &Rect,
+        // horizontal offsets of the bottom and top vertices
+        shear: Vec2,
+        uv: &Rect,
+        layer: u32,
+        color: &[f32; 4],
+        weight: f32,
+    ) {
         if let Some(VertexAttributeValues::Float32x2(vertices)) =
             self.mesh.attribute_mut(ATTRIBUTE_TEXT_POSITION)
         {
             let rect = *position;
-            vertices.push([rect.min.x, rect.min.y]);
-            vertices.push([rect.max.x, rect.min.y]);
-            vertices.push([rect.max.x, rect.max.y]);
-            vertices.push([rect.min.x, rect.max.y]);
+            vertices.push([rect.min.x + shear.x, rect.min.y]);
+            vertices.push([rect.max.x + shear.x, rect.min.y]);
+            vertices.push([rect.max.x + shear.y, rect.max.y]);
+            vertices.push([rect.min.x + shear.y, rect.max.y]);
         }
 
         if let Some(VertexAttributeValues::Float32x2(uvs)) =
@@ -226,6 +724,18 @@ impl<'a> TextMeshBuilder<'a> {
             colors.extend([*color; 4]); // FIXME: this wastes a ton of memory..
         }
 
+        if let Some(VertexAttributeValues::Uint32(layers)) =
+            self.mesh.attribute_mut(ATTRIBUTE_TEXT_LAYER)
+        {
+            layers.extend([layer; 4]);
+        }
+
+        if let Some(VertexAttributeValues::Float32(weights)) =
+            self.mesh.attribute_mut(ATTRIBUTE_TEXT_WEIGHT)
+        {
+            weights.extend([weight; 4]);
+        }
+
         if let Some(Indices::U32(indices)) = self.mesh.indices_mut() {
             let base = self.index * 4;
             indices.extend([base + 0, base + 1, base + 3, base + 1, base + 2, base + 3]);
@@ -234,3 +744,60 @@ impl<'a> TextMeshBuilder<'a> {
         self.index += 1;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{Events, World};
+
+    use super::super::{Text3d, Text3dSpan, TextSpanStyle};
+    use super::*;
+    use crate::font::test_font;
+
+    fn span(font: &Handle<Font>) -> Text3dSpan {
+        Text3dSpan::new(" ab").with_style(TextSpanStyle {
+            font: Some(font.clone()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    #[should_panic = "a font family needs at least its primary font"]
+    fn rejects_an_empty_family() {
+        TextMesh::from_family(&FontFamily { fonts: Vec::new() });
+    }
+
+    #[test]
+    fn shrinking_span_fonts_drops_their_glyphs() {
+        let mut world = World::new();
+        world.init_resource::<Assets<Mesh>>();
+        world.init_resource::<Events<AssetEvent<Font>>>();
+        world.init_resource::<Events<TextMeshComplete>>();
+        world.init_resource::<FontAtlases>();
+        let mut fonts = Assets::<Font>::default();
+        let [family, first, second] = [(); 3].map(|_| fonts.add(test_font::font()));
+        world.insert_resource(fonts);
+
+        let text = Text3d::new("ab")
+            .with_span(span(&first))
+            .with_span(span(&second));
+        let entity = world.spawn((text, TextMesh::new(family))).id();
+        world.run_system_once(super::super::layout::update_text_layout);
+        let text_mesh = world.get::<TextMesh>(entity).unwrap();
+        assert_eq!(text_mesh.fonts().len(), 3);
+        assert!(text_mesh.glyphs.iter().any(|glyph| glyph.font == 2));
+
+        // a single span font that is not loaded yet
+        let unloaded = Handle::weak_from_u128(0x5EED);
+        world.get_mut::<Text3d>(entity).unwrap().spans = vec![span(&unloaded)];
+        world.run_system_once(super::super::layout::update_text_layout);
+        let text_mesh = world.get::<TextMesh>(entity).unwrap();
+        assert_eq!(text_mesh.fonts().len(), 2);
+        assert!(text_mesh.glyphs.is_empty());
+        assert!(text_mesh.decorations.is_empty());
+        assert!(text_mesh.missing.iter().all(|(font, _)| *font < 1));
+
+        world.run_system_once(update_glyph_users);
+        world.run_system_once(update_text_mesh);
+    }
+}